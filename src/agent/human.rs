@@ -8,9 +8,20 @@ use bevy::{
     transform::components::Transform,
 };
 
-use crate::AnimationTimer;
+use crate::{
+    spell::{
+        casting::Cooldowns,
+        interaction::ObjectKind,
+        mana::Mana,
+        parser::Dialect,
+        spellbook::Spellbook,
+        temperature::{new_thermal_bundle, ThermalBundle, AMBIENT},
+        Fluency, Practice,
+    },
+    AnimationTimer,
+};
 
-use super::{Action, AnimationSet, CharacterState, Direction};
+use super::{Action, AnimationSet, CharacterState, Direction, Health, MAX_HEALTH};
 
 pub type HumanAgentBundle = (
     SpriteSheetBundle,
@@ -18,6 +29,14 @@ pub type HumanAgentBundle = (
     AnimationTimer,
     CharacterState,
     HumanController,
+    ThermalBundle,
+    Health,
+    Mana,
+    Cooldowns,
+    ObjectKind,
+    Fluency,
+    Practice,
+    Spellbook,
 );
 
 #[derive(Component)]
@@ -42,5 +61,14 @@ pub fn new_human_agent_bundle(
             direction: Direction::N,
         },
         HumanController {},
+        new_thermal_bundle(AMBIENT),
+        Health::new(MAX_HEALTH),
+        Mana::default(),
+        Cooldowns::default(),
+        ObjectKind::Person,
+        // Everything past the basics is learnt by casting
+        Fluency(Dialect::Apprentice),
+        Practice::default(),
+        Spellbook::default(),
     )
 }
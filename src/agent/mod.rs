@@ -1,7 +1,9 @@
 use bevy::{
     ecs::{
         component::Component,
+        entity::Entity,
         event::Event,
+        query::Without,
         system::{Query, Res},
     },
     math::{Vec2, Vec3},
@@ -12,11 +14,16 @@ use bevy::{
     transform::components::Transform,
 };
 
-use crate::AnimationTimer;
+use crate::{
+    spell::{lift::Lifted, shape::Obstacle, temperature::ThermalState},
+    AnimationTimer,
+};
 
 pub mod human;
 pub mod npc;
 
+pub const MAX_HEALTH: f32 = 100.0;
+
 pub const SKELETON: AnimationSet = AnimationSet {
     running: AnimationIndices { first: 4, last: 11 },
     idle: AnimationIndices { first: 0, last: 3 },
@@ -61,6 +68,22 @@ pub struct AnimationIndices {
     last: usize,
 }
 
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Health { current: max, max }
+    }
+
+    pub fn damage(&mut self, amount: f32) {
+        self.current = (self.current - amount.max(0.0)).max(0.0);
+    }
+}
+
 #[derive(Component, PartialEq, Eq)]
 pub struct CharacterState {
     pub action: Action,
@@ -82,17 +105,41 @@ impl Direction {
     }
 }
 
-pub fn move_agent(mut query: Query<(&mut Transform, &mut CharacterState)>, time: Res<Time>) {
-    for (mut player_transform, character_state) in &mut query {
+// Anything lifted off the ground can't walk and is out of the way until it lands
+type Grounded = (Without<Obstacle>, Without<Lifted>);
+
+pub fn move_agent(
+    mut query: Query<(&mut Transform, &mut CharacterState, Option<&ThermalState>), Grounded>,
+    obstacles: Query<(&Transform, &Obstacle), Without<Lifted>>,
+    time: Res<Time>,
+) {
+    for (mut player_transform, character_state, thermal_state) in &mut query {
         if character_state.action != Action::Running {
             continue;
         }
 
+        if thermal_state == Some(&ThermalState::Frozen) {
+            continue;
+        }
+
         let character_direction = character_state.direction.as_vec();
         let (x, y) = (character_direction.x, character_direction.y);
 
-        player_transform.translation.x += x * 170f32 * time.delta_seconds();
-        player_transform.translation.y += y * 170f32 * time.delta_seconds();
+        let current = player_transform.translation.truncate();
+        let next = current + Vec2::new(x, y) * 170f32 * time.delta_seconds();
+
+        // Only block moves that go further into an obstacle so nothing gets stuck inside one
+        let blocked = obstacles.iter().any(|(transform, obstacle)| {
+            let centre = transform.translation.truncate();
+            next.distance(centre) < obstacle.radius
+                && next.distance(centre) < current.distance(centre)
+        });
+        if blocked {
+            continue;
+        }
+
+        player_transform.translation.x = next.x;
+        player_transform.translation.y = next.y;
     }
 }
 
@@ -148,3 +195,9 @@ pub fn make_speech_bubble(text_style: TextStyle) -> Text2dBundle {
 pub struct Shout {
     pub message: String,
 }
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct Damaged {
+    pub entity: Entity,
+    pub amount: f32,
+}
@@ -15,9 +15,22 @@ use bevy::{
 };
 use uuid::Uuid;
 
-use crate::{generator::Conversation, oracle::CompletionCallback, AnimationTimer, Game};
+use crate::{
+    generator::Conversation,
+    oracle::CompletionCallback,
+    spell::{
+        casting::Cooldowns,
+        interaction::ObjectKind,
+        mana::Mana,
+        parser::Dialect,
+        spellbook::Spellbook,
+        temperature::{new_thermal_bundle, ThermalBundle, AMBIENT},
+        Fluency,
+    },
+    AnimationTimer, Game,
+};
 
-use super::{Action, AnimationSet, CharacterState, Direction, Shout};
+use super::{Action, AnimationSet, CharacterState, Direction, Health, Shout, MAX_HEALTH};
 
 #[derive(Component)]
 pub struct AiController {
@@ -222,9 +235,11 @@ pub fn tick_ai(
 
                     controller.active_converstation = Some(conversation);
 
+                    // Not every child is the speech bubble, a lifted agent also carries a shadow
                     for child in children.iter() {
-                        let mut text = text_query.get_mut(*child).unwrap();
-                        text.sections[0].value = character_float_text.clone();
+                        if let Ok(mut text) = text_query.get_mut(*child) {
+                            text.sections[0].value = character_float_text.clone();
+                        }
                     }
 
                     (Action::Idle, Direction::S)
@@ -243,6 +258,13 @@ pub type AiAgentBundle = (
     AnimationTimer,
     CharacterState,
     AiController,
+    ThermalBundle,
+    Health,
+    Mana,
+    Cooldowns,
+    ObjectKind,
+    Fluency,
+    Spellbook,
 );
 
 pub fn new_ai_agent_bundle(
@@ -269,5 +291,12 @@ pub fn new_ai_agent_bundle(
             active_converstation: None,
             ai_state: AiState::Patrolling(Action::Idle, Direction::N),
         },
+        new_thermal_bundle(AMBIENT),
+        Health::new(MAX_HEALTH),
+        Mana::default(),
+        Cooldowns::default(),
+        ObjectKind::Person,
+        Fluency(Dialect::Apprentice),
+        Spellbook::default(),
     )
 }
@@ -0,0 +1,334 @@
+// Checks spells without opening the game: prints what each spell parses to, what it costs and
+// anything that looks wrong with it, and with --simulate casts it in a headless world and prints
+// the events that come out.
+//
+//     spellc [--simulate] [--seconds N] [FILE]
+//
+// Spells are read one per line from FILE, or stdin without one. Blank lines and lines starting
+// with # are skipped, "define" lines add spell words for the lines after them.
+
+use std::{
+    io::Read,
+    process::ExitCode,
+    time::{Duration, Instant},
+};
+
+use bevy::{ecs::system::RunSystemOnce, prelude::*, time::TimeUpdateStrategy};
+use spellfire::{
+    agent::Damaged,
+    spell::{
+        casting::{interrupt_spells, tick_cooldowns},
+        dispel::CounterAttempt,
+        focus::SelectedTarget,
+        history::SpellCast,
+        interaction::{touch_fire, InteractionTable, Interactions, InteractionsPlugin},
+        lifetime::expire_lifetimes,
+        lift::{apply_impact_damage, fall_lifted, Landed},
+        lint::lint,
+        mana::{regenerate_mana, spell_cost},
+        parser::{is_definition, parse_spell_with, Dialect, SpellAst},
+        push::apply_knockback,
+        shape::move_projectiles,
+        spellbook::Spellbook,
+        status::{
+            burn, expire_status, freeze, levitate, squeeze, Burning, Compressed, Frozen, Levitating,
+        },
+        temperature::{
+            catch_fire, exchange_ground_heat, react_to_temperature, spread_temperature,
+            spread_tile_temperature, ThermalReaction,
+        },
+        update_spell, Spell, SpellFinished, SpellRejected,
+    },
+    EntityFactory, Game, NamedAssets,
+};
+
+// The simulation steps at a fixed rate so the same spell always prints the same events
+const FRAME: Duration = Duration::from_millis(16);
+const DEFAULT_SECONDS: f32 = 10.0;
+// How long to wait on the interaction table before simulating without it
+const LOAD_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Options {
+    simulate: bool,
+    seconds: f32,
+    path: Option<String>,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        simulate: false,
+        seconds: DEFAULT_SECONDS,
+        path: None,
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--simulate" => options.simulate = true,
+            "--seconds" => {
+                options.seconds = args
+                    .next()
+                    .and_then(|seconds| seconds.parse().ok())
+                    .ok_or("--seconds needs a number")?;
+            }
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+            _ => options.path = Some(arg),
+        }
+    }
+
+    Ok(options)
+}
+
+fn read_input(path: Option<&str>) -> std::io::Result<String> {
+    match path {
+        Some(path) => std::fs::read_to_string(path),
+        None => {
+            let mut input = String::new();
+            std::io::stdin().read_to_string(&mut input)?;
+            Ok(input)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}", error);
+            eprintln!("usage: spellc [--simulate] [--seconds N] [FILE]");
+            return ExitCode::from(2);
+        }
+    };
+
+    let input = match read_input(options.path.as_deref()) {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("Could not read spells: {}", error);
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut failed = false;
+    let mut spellbook = Spellbook::default();
+
+    let spells = input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+
+    for spell in spells {
+        if is_definition(spell) {
+            match spellbook.define(spell, Dialect::LATEST) {
+                Ok(word) => println!("defined: {}\n", word),
+                Err(error) => {
+                    println!("error: {}\n", error);
+                    failed = true;
+                }
+            }
+            continue;
+        }
+
+        println!("spell: {}", spell);
+
+        let ast = match parse_spell_with(spell, Dialect::LATEST, &spellbook) {
+            Ok(ast) => ast,
+            Err(error) => {
+                println!("error: {}\n", error);
+                failed = true;
+                continue;
+            }
+        };
+
+        println!("ast: {:#?}", ast);
+        println!("cost: {} mana", spell_cost(&ast));
+
+        for warning in lint(&ast) {
+            println!("warning: {}", warning);
+        }
+
+        if options.simulate {
+            for line in simulate(spell, ast, options.seconds) {
+                println!("  {}", line);
+            }
+        }
+
+        println!();
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+#[derive(Resource, Default)]
+struct Report {
+    cast_at: Duration,
+    lines: Vec<String>,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn report(
+    time: Res<Time>,
+    mut log: ResMut<Report>,
+    mut rejections: EventReader<SpellRejected>,
+    mut finished: EventReader<SpellFinished>,
+    mut casts: EventReader<SpellCast>,
+    mut landings: EventReader<Landed>,
+    mut damaged: EventReader<Damaged>,
+    mut reactions: EventReader<ThermalReaction>,
+    mut counters: EventReader<CounterAttempt>,
+) {
+    let at = (time.elapsed() - log.cast_at).as_secs_f32();
+    let mut record = |event: String| log.lines.push(format!("[{:6.2}s] {}", at, event));
+
+    rejections
+        .read()
+        .for_each(|event| record(format!("{:?}", event)));
+    finished
+        .read()
+        .for_each(|event| record(format!("{:?}", event)));
+    casts
+        .read()
+        .for_each(|event| record(format!("{:?}", event)));
+    landings
+        .read()
+        .for_each(|event| record(format!("{:?}", event)));
+    damaged
+        .read()
+        .for_each(|event| record(format!("{:?}", event)));
+    reactions
+        .read()
+        .for_each(|event| record(format!("{:?}", event)));
+    counters
+        .read()
+        .for_each(|event| record(format!("{:?}", event)));
+}
+
+// A caster, a selected rock to aim at and a little clutter around them
+fn setup(mut commands: Commands, texture_assets: ResMut<Assets<TextureAtlas>>) {
+    let assets = NamedAssets {
+        character: default(),
+        fire: default(),
+        rock: default(),
+        items: default(),
+        trees: default(),
+        font: default(),
+    };
+    let entity_factory = EntityFactory::new(assets, texture_assets);
+
+    let mut target = entity_factory.make_rock();
+    target.0.transform.translation = Vec3::new(100.0, 0.0, 10.0);
+    let target = commands.spawn(target).id();
+
+    for position in [Vec2::new(60.0, 80.0), Vec2::new(140.0, 60.0)] {
+        let mut rock = entity_factory.make_rock();
+        rock.0.transform.translation = position.extend(10.0);
+        commands.spawn(rock);
+    }
+
+    let mut tree = entity_factory.make_tree();
+    tree.0.transform.translation = Vec3::new(-120.0, 40.0, 10.0);
+    commands.spawn(tree);
+
+    let (mut human, _) = entity_factory.make_human();
+    human.0.transform.translation = Vec3::new(0.0, 0.0, 10.0);
+    commands.spawn((human, SelectedTarget(target)));
+
+    commands.insert_resource(Game::offline(entity_factory));
+}
+
+fn simulate(source: &str, ast: SpellAst, seconds: f32) -> Vec<String> {
+    let mut app = App::new();
+
+    app.add_plugins((MinimalPlugins, AssetPlugin::default(), InteractionsPlugin))
+        .init_asset::<TextureAtlas>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME))
+        .init_resource::<Report>()
+        .add_event::<SpellRejected>()
+        .add_event::<SpellFinished>()
+        .add_event::<SpellCast>()
+        .add_event::<CounterAttempt>()
+        .add_event::<ThermalReaction>()
+        .add_event::<Landed>()
+        .add_event::<Damaged>();
+
+    // The interaction table loads in the background, the world and its systems only go in once
+    // it's there so however long that takes can't change what happens
+    let started = Instant::now();
+    while !interactions_loaded(&mut app) {
+        if started.elapsed() > LOAD_TIMEOUT {
+            app.world
+                .resource_mut::<Report>()
+                .lines
+                .push("interaction table didn't load, simulating without it".to_string());
+            break;
+        }
+        app.update();
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    app.world.run_system_once(setup);
+
+    app.add_systems(
+        Update,
+        (
+            update_spell,
+            interrupt_spells
+                .after(apply_impact_damage)
+                .before(update_spell),
+            (tick_cooldowns, expire_lifetimes, regenerate_mana),
+            (move_projectiles, touch_fire).chain(),
+            apply_knockback,
+            (fall_lifted, apply_impact_damage).chain(),
+            (
+                spread_temperature,
+                exchange_ground_heat,
+                spread_tile_temperature,
+                react_to_temperature,
+                catch_fire,
+            )
+                .chain(),
+            (
+                expire_status::<Burning>,
+                expire_status::<Levitating>,
+                expire_status::<Frozen>,
+                expire_status::<Compressed>,
+                burn.before(interrupt_spells),
+                levitate.after(fall_lifted),
+                freeze
+                    .after(spread_tile_temperature)
+                    .before(react_to_temperature),
+                squeeze,
+            ),
+        ),
+    )
+    .add_systems(PostUpdate, report);
+
+    let caster = app
+        .world
+        .query_filtered::<Entity, With<SelectedTarget>>()
+        .single(&app.world);
+    app.world
+        .spawn(Spell::from(ast).with_source(source).cast_by(caster));
+    app.world.resource_mut::<Report>().cast_at = app.world.resource::<Time>().elapsed();
+
+    let frames = (seconds / FRAME.as_secs_f32()).ceil() as usize;
+    for _ in 0..frames {
+        app.update();
+    }
+
+    std::mem::take(&mut app.world.resource_mut::<Report>().lines)
+}
+
+fn interactions_loaded(app: &mut App) -> bool {
+    let (Some(interactions), Some(tables)) = (
+        app.world.get_resource::<Interactions>(),
+        app.world.get_resource::<Assets<InteractionTable>>(),
+    ) else {
+        return false;
+    };
+
+    tables.contains(&interactions.0)
+}
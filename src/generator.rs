@@ -64,9 +64,13 @@ impl Default for Conversation {
 
 impl Conversation {
     pub fn new() -> Self {
+        Self::with_system_prompt("You are Hamish the sentient skeleton, you're generally relatively grumpy and are short with people who try to interrupt your patrol. Keep your response terse")
+    }
+
+    pub fn with_system_prompt(prompt: &str) -> Self {
         let initiating_message = Message {
             role: Role::System,
-            content: "You are Hamish the sentient skeleton, you're generally relatively grumpy and are short with people who try to interrupt your patrol. Keep your response terse".to_string(),
+            content: prompt.to_string(),
         };
 
         Self {
@@ -0,0 +1,288 @@
+pub mod agent;
+pub mod camera;
+pub mod generator;
+pub mod oracle;
+pub mod spell;
+pub mod terrain;
+
+use agent::human::{new_human_agent_bundle, HumanAgentBundle};
+use agent::npc::{new_ai_agent_bundle, AiAgentBundle};
+use agent::{make_speech_bubble, SKELETON};
+
+use bevy::prelude::*;
+use generator::CompletionQuery;
+use oracle::{start_oracle, Oracle};
+use spell::{
+    interaction::ObjectKind,
+    new_matter_blob_bundle,
+    shape::{
+        new_obstacle_bundle, new_pickup_bundle, new_projectile_bundle, ObstacleBundle,
+        PickupBundle, ProjectileBundle, ARROW_SPRITE, SWORD_SPRITE, TREE_SPRITE,
+    },
+    status::{new_status_overlay_bundle, StatusKind, StatusOverlayBundle},
+    temperature::{AMBIENT, FIRE_TEMPERATURE},
+    MatterBlobBundleBundle,
+};
+use std::sync::mpsc::{channel, Sender};
+use uuid::Uuid;
+
+#[derive(Default, Debug, Eq, PartialEq)]
+pub enum GameState {
+    #[default]
+    Loading,
+    Playing,
+    Typing,
+}
+
+#[derive(Resource)]
+pub struct Game {
+    pub game_state: GameState,
+    pub asker: Sender<(Uuid, CompletionQuery)>,
+    pub oracle: Oracle,
+    pub entity_factory: Option<EntityFactory>,
+}
+
+impl Game {
+    // A game that never asks the oracle anything, for running spells without a window or an
+    // API key
+    pub fn offline(entity_factory: EntityFactory) -> Self {
+        let (asker, _) = channel();
+
+        Game {
+            game_state: GameState::Playing,
+            asker,
+            oracle: Oracle {
+                completion_queue: default(),
+            },
+            entity_factory: Some(entity_factory),
+        }
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        let (asker, oracle) = start_oracle();
+
+        Game {
+            game_state: GameState::Loading,
+            asker,
+            oracle,
+            entity_factory: None,
+        }
+    }
+}
+
+#[derive(Component, Deref, DerefMut)]
+pub struct AnimationTimer(pub Timer);
+
+pub struct EntityFactory {
+    constructed_assets: ConstructedAssets,
+}
+
+pub struct NamedAssets {
+    pub character: Handle<Image>,
+    pub fire: Handle<Image>,
+    pub rock: Handle<Image>,
+    pub items: Handle<Image>,
+    pub trees: Handle<Image>,
+    pub font: Handle<Font>,
+}
+
+struct ConstructedAssets {
+    pub character_atlas: Handle<TextureAtlas>,
+    pub fire_atlas: Handle<TextureAtlas>,
+    pub rock_atlas: Handle<TextureAtlas>,
+    pub item_atlas: Handle<TextureAtlas>,
+    pub tree_atlas: Handle<TextureAtlas>,
+    pub text_style: TextStyle,
+}
+
+impl EntityFactory {
+    pub fn new(
+        named_assets: NamedAssets,
+        mut texture_assets: ResMut<Assets<TextureAtlas>>,
+    ) -> Self {
+        let character_atlas = TextureAtlas::from_grid(
+            named_assets.character.clone(),
+            Vec2::new(128.0, 128.0),
+            24,
+            8,
+            None,
+            None,
+        );
+
+        let character_atlas_handle = texture_assets.add(character_atlas);
+
+        let fire_atlas = TextureAtlas::from_grid(
+            named_assets.fire.clone(),
+            Vec2::new(32.0, 32.0),
+            3,
+            1,
+            None,
+            None,
+        );
+
+        let fire_atlas_handle = texture_assets.add(fire_atlas);
+
+        let rock_atlas = TextureAtlas::from_grid(
+            named_assets.rock.clone(),
+            Vec2::new(32.0, 32.0),
+            5,
+            1,
+            None,
+            None,
+        );
+
+        let rock_atlas_handle = texture_assets.add(rock_atlas);
+
+        let item_atlas = TextureAtlas::from_grid(
+            named_assets.items.clone(),
+            Vec2::new(32.0, 32.0),
+            8,
+            8,
+            None,
+            None,
+        );
+
+        let item_atlas_handle = texture_assets.add(item_atlas);
+
+        let tree_atlas = TextureAtlas::from_grid(
+            named_assets.trees.clone(),
+            Vec2::new(128.0, 128.0),
+            4,
+            4,
+            None,
+            None,
+        );
+
+        let tree_atlas_handle = texture_assets.add(tree_atlas);
+
+        let constructed_assets = ConstructedAssets {
+            character_atlas: character_atlas_handle,
+            rock_atlas: rock_atlas_handle.clone(),
+            fire_atlas: fire_atlas_handle,
+            item_atlas: item_atlas_handle,
+            tree_atlas: tree_atlas_handle,
+            text_style: TextStyle {
+                font: named_assets.font.clone(),
+                font_size: 15.0,
+                color: Color::RED,
+            },
+        };
+
+        EntityFactory { constructed_assets }
+    }
+
+    pub fn make_human(&self) -> (HumanAgentBundle, Text2dBundle) {
+        (
+            new_human_agent_bundle(
+                self.constructed_assets.character_atlas.clone(),
+                SKELETON.clone(),
+            ),
+            make_speech_bubble(self.constructed_assets.text_style.clone()),
+        )
+    }
+
+    pub fn make_ai(&self) -> (AiAgentBundle, Text2dBundle) {
+        (
+            new_ai_agent_bundle(
+                self.constructed_assets.character_atlas.clone(),
+                SKELETON.clone(),
+            ),
+            make_speech_bubble(self.constructed_assets.text_style.clone()),
+        )
+    }
+
+    pub fn make_rock(&self) -> MatterBlobBundleBundle {
+        new_matter_blob_bundle(
+            self.constructed_assets.rock_atlas.clone(),
+            SKELETON.clone(),
+            AMBIENT,
+            ObjectKind::Rock,
+        )
+    }
+
+    pub fn make_fire(&self) -> MatterBlobBundleBundle {
+        new_matter_blob_bundle(
+            self.constructed_assets.fire_atlas.clone(),
+            SKELETON.clone(),
+            FIRE_TEMPERATURE,
+            ObjectKind::Fire,
+        )
+    }
+
+    pub fn make_sword(&self) -> PickupBundle {
+        new_pickup_bundle(self.constructed_assets.item_atlas.clone(), SWORD_SPRITE)
+    }
+
+    pub fn make_arrow(&self) -> ProjectileBundle {
+        new_projectile_bundle(self.constructed_assets.item_atlas.clone(), ARROW_SPRITE)
+    }
+
+    pub fn make_tree(&self) -> ObstacleBundle {
+        new_obstacle_bundle(self.constructed_assets.tree_atlas.clone(), TREE_SPRITE)
+    }
+
+    pub fn make_status_overlay(&self, kind: StatusKind) -> StatusOverlayBundle {
+        let assets = &self.constructed_assets;
+
+        match kind {
+            StatusKind::Burning => {
+                new_status_overlay_bundle(assets.fire_atlas.clone(), 0, Color::WHITE)
+            }
+            StatusKind::Levitating => new_status_overlay_bundle(
+                assets.fire_atlas.clone(),
+                1,
+                Color::rgba(0.7, 0.9, 1.0, 0.5),
+            ),
+            StatusKind::Frozen => new_status_overlay_bundle(
+                assets.rock_atlas.clone(),
+                0,
+                Color::rgba(0.6, 0.8, 1.0, 0.5),
+            ),
+            StatusKind::Compressed => new_status_overlay_bundle(
+                assets.rock_atlas.clone(),
+                4,
+                Color::rgba(0.5, 0.5, 0.5, 0.6),
+            ),
+        }
+    }
+
+    // What an object of the kind looks like, for things that turn into one without being made
+    // afresh. A person is more than a sprite so there's no looking like one.
+    pub fn appearance(
+        &self,
+        kind: ObjectKind,
+    ) -> Option<(Handle<TextureAtlas>, TextureAtlasSprite)> {
+        let assets = &self.constructed_assets;
+
+        let appearance = match kind {
+            ObjectKind::Rock => (assets.rock_atlas.clone(), TextureAtlasSprite::new(0)),
+            ObjectKind::Fire => (assets.fire_atlas.clone(), TextureAtlasSprite::new(0)),
+            // Fire packed small and white hot
+            ObjectKind::Fireball => (
+                assets.fire_atlas.clone(),
+                TextureAtlasSprite {
+                    color: Color::rgb(1.0, 0.9, 0.6),
+                    custom_size: Some(Vec2::splat(20.0)),
+                    ..TextureAtlasSprite::new(0)
+                },
+            ),
+            ObjectKind::Tree => (
+                assets.tree_atlas.clone(),
+                TextureAtlasSprite::new(TREE_SPRITE),
+            ),
+            ObjectKind::Sword => (
+                assets.item_atlas.clone(),
+                TextureAtlasSprite::new(SWORD_SPRITE),
+            ),
+            ObjectKind::Arrow => (
+                assets.item_atlas.clone(),
+                TextureAtlasSprite::new(ARROW_SPRITE),
+            ),
+            ObjectKind::Person => return None,
+        };
+
+        Some(appearance)
+    }
+}
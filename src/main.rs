@@ -1,66 +1,65 @@
-mod agent;
-mod generator;
-mod oracle;
-mod spell;
-mod terrain;
-mod camera;
-
-use agent::human::{new_human_agent_bundle, HumanAgentBundle, HumanController};
-use agent::npc::{new_ai_agent_bundle, tick_ai, AiAgentBundle};
-use agent::{
-    animate_sprite, make_speech_bubble, move_agent, Action, CharacterState, Shout, SKELETON,
-};
+use spellfire::{agent, camera, oracle, spell, terrain};
+use spellfire::{EntityFactory, Game, GameState, NamedAssets};
+
+use agent::human::HumanController;
+use agent::npc::tick_ai;
+use agent::{animate_sprite, move_agent, Action, CharacterState, Damaged, Shout};
 
 use bevy::prelude::*;
 use bevy::window::WindowMode;
 use bevy_ecs_tilemap::TilemapPlugin;
 use camera::move_camera;
-use oracle::{read_oracle, start_oracle, CompletionCallback, Oracle, OracleReaderConfig};
+use oracle::{read_oracle, CompletionCallback, OracleReaderConfig};
 use spell::{
-    animate_blob, create_spell, new_matter_blob_bundle, update_spell, MatterBlobBundleBundle,
+    animate_blob,
+    casting::{interrupt_spells, tick_cooldowns},
+    dispel::{counter_threats, CounterAttempt},
+    focus::SelectedTarget,
+    history::{record_casts, CastHistory, SpellCast, CAST_HISTORY_EXPORT},
+    interaction::{touch_fire, InteractionsPlugin},
+    lifetime::expire_lifetimes,
+    lift::{apply_impact_damage, crater_ground, fall_lifted, Landed},
+    mana::regenerate_mana,
+    push::apply_knockback,
+    shape::{collect_pickups, move_projectiles},
+    temperature::{
+        catch_fire, exchange_ground_heat, react_to_temperature, spread_temperature,
+        spread_tile_temperature, ThermalReaction,
+    },
+    translate::{is_free_text, receive_translations, PendingTranslations},
+    parser::{is_definition, parse_spell_with, Dialect},
+    spellbook::{Spellbook, SpellbookError, PLAYER_SPELLBOOK},
+    status::{
+        burn, expire_status, freeze, levitate, squeeze, Burning, Compressed, Frozen, Levitating,
+        StatusOverlay,
+    },
+    targeting::{draw_selection, frame_bounds, pick},
+    load_fluency, practise, save_fluency, update_spell, Fluency, FluencyError, FluencyGained,
+    Practice, Rejection, Spell, SpellFinished, SpellRejected, PLAYER_FLUENCY,
 };
-use std::sync::mpsc::Sender;
 use std::time::Duration;
-use terrain::{TiledMap, TiledMapBundle, TiledMapPlugin};
-use uuid::Uuid;
+use terrain::{
+    TerrainAlterations, TerrainAlterationsError, TiledMap, TiledMapBundle, TiledMapPlugin,
+    TERRAIN_ALTERATIONS,
+};
 
-use crate::generator::CompletionQuery;
+#[derive(Component, Default)]
+struct InputText;
 
-#[derive(Default, Debug, Eq, PartialEq)]
-enum GameState {
-    #[default]
-    Loading,
-    Playing,
-    Typing,
-}
+#[derive(Component)]
+struct CastHistoryPanel;
 
-#[derive(Resource)]
-struct Game {
-    game_state: GameState,
-    asker: Sender<(Uuid, CompletionQuery)>,
-    oracle: Oracle,
-    entity_factory: Option<EntityFactory>,
-}
+// How many of the latest casts the panel shows
+const CAST_HISTORY_SHOWN: usize = 8;
 
-impl Default for Game {
-    fn default() -> Self {
-        let (asker, oracle) = start_oracle();
+// Lines typed with this in front are cast as spells rather than shouted
+const CAST_PREFIX: char = '/';
 
-        Game {
-            game_state: GameState::Loading,
-            asker,
-            oracle,
-            entity_factory: None,
-        }
-    }
+#[derive(Event)]
+struct Incantation {
+    message: String,
 }
 
-#[derive(Component, Deref, DerefMut)]
-struct AnimationTimer(Timer);
-
-#[derive(Component, Default)]
-struct InputText;
-
 fn keyboard_to_direction<'a>(
     key_events: impl ExactSizeIterator<Item = &'a KeyCode>,
 ) -> Option<agent::Direction> {
@@ -95,7 +94,10 @@ fn control_player(
         return;
     }
 
-    let (mut _controller, mut character_state) = query.single_mut();
+    // The player may have been turned into something that can't be controlled
+    let Ok((mut _controller, mut character_state)) = query.get_single_mut() else {
+        return;
+    };
 
     if let Some(direction) = keyboard_to_direction(keyboard_input.get_pressed()) {
         character_state.direction = direction;
@@ -132,11 +134,22 @@ fn toggle_text_input(mut game: ResMut<Game>, kbd: Res<Input<KeyCode>>) {
     }
 }
 
+type Clickable<'a> = (
+    Entity,
+    &'a GlobalTransform,
+    &'a TextureAtlasSprite,
+    &'a Handle<TextureAtlas>,
+);
+
+// Clicking a sprite makes it the player's target, clicking empty ground clears the selection
 fn handle_mouse(
+    mut commands: Commands,
     camera_query: Query<(&Camera, &GlobalTransform)>,
     windows: Query<&Window>,
     buttons: Res<Input<MouseButton>>,
-    q_sprites: Query<(Entity, &Transform, &CharacterState)>,
+    atlases: Res<Assets<TextureAtlas>>,
+    player_query: Query<Entity, With<HumanController>>,
+    q_sprites: Query<Clickable, (Without<HumanController>, Without<StatusOverlay>)>,
 ) {
     if buttons.just_pressed(MouseButton::Left) {
         let (camera, camera_transform) = camera_query.single();
@@ -149,13 +162,20 @@ fn handle_mouse(
             return;
         };
 
-        for (_entity, transform, _sprite) in q_sprites.iter() {
-            let min = transform.translation.truncate() - Vec2::new(128.0, 64.0);
-            let max = transform.translation.truncate() + Vec2::new(128.0, 64.0);
+        let sprites = q_sprites
+            .iter()
+            .filter_map(|(entity, transform, sprite, handle)| {
+                let bounds = frame_bounds(atlases.get(handle)?, sprite, transform)?;
+                Some((entity, bounds, transform.translation()))
+            });
 
-            if point.x >= min.x && point.x <= max.x && point.y >= min.y && point.y <= max.y {
-                println!("Clicked an entity");
-            }
+        let selected = pick(point, sprites);
+
+        for player in player_query.iter() {
+            match selected {
+                Some(entity) => commands.entity(player).insert(SelectedTarget(entity)),
+                None => commands.entity(player).remove::<SelectedTarget>(),
+            };
         }
     }
 }
@@ -166,7 +186,8 @@ fn text_input(
     kbd: Res<Input<KeyCode>>,
     mut string: Local<String>,
     mut query: Query<(&InputText, &mut Text)>,
-    mut event_writer: EventWriter<Shout>,
+    mut shouts: EventWriter<Shout>,
+    mut incantations: EventWriter<Incantation>,
 ) {
     if game.game_state != GameState::Typing {
         return;
@@ -177,116 +198,203 @@ fn text_input(
         if message.trim().is_empty() {
             return;
         }
-        event_writer.send(Shout { message });
+
+        if let Some(spell) = message.trim_start().strip_prefix(CAST_PREFIX) {
+            incantations.send(Incantation {
+                message: spell.to_string(),
+            });
+        } else {
+            shouts.send(Shout { message });
+        }
         string.clear();
     }
     if kbd.just_pressed(KeyCode::Back) {
         string.pop();
     }
+    let mut edited = false;
     for ev in evr_char.read() {
         if !ev.char.is_control() {
             string.push(ev.char);
+            edited = true;
         }
     }
 
     for (_input, mut text) in &mut query {
         text.sections[0].value = string.to_string();
+        if edited {
+            text.sections[1].value.clear();
+        }
     }
 }
 
-struct EntityFactory {
-    constructed_assets: ConstructedAssets,
+// Spells that don't parse are shown the error straight away, loose wording that doesn't look like
+// a spell at all is handed to the oracle to translate
+fn cast_incantations(
+    mut commands: Commands,
+    game: Res<Game>,
+    mut incantations: EventReader<Incantation>,
+    mut translations: ResMut<PendingTranslations>,
+    mut player_query: Query<(Entity, &Fluency, &mut Spellbook), With<HumanController>>,
+    mut rejections: EventWriter<SpellRejected>,
+) {
+    let Ok((player, fluency, mut spellbook)) = player_query.get_single_mut() else {
+        return;
+    };
+
+    for incantation in incantations.read() {
+        if is_definition(&incantation.message) {
+            match spellbook.define(&incantation.message, fluency.0) {
+                Ok(_) => {
+                    if let Err(error) = spellbook.save(PLAYER_SPELLBOOK) {
+                        warn!("{}", error);
+                    }
+                }
+                Err(error) => rejections.send(SpellRejected {
+                    caster: Some(player),
+                    rejection: Rejection::Parse(error),
+                }),
+            }
+            continue;
+        }
+
+        match parse_spell_with(&incantation.message, fluency.0, &spellbook) {
+            Ok(ast) => {
+                let spell = Spell::from(ast).with_source(&incantation.message);
+                commands.spawn(spell.cast_by(player));
+            }
+            Err(_) if is_free_text(&incantation.message, &spellbook) => {
+                translations.start(&game.asker, player, fluency.0, &incantation.message)
+            }
+            Err(error) => rejections.send(SpellRejected {
+                caster: Some(player),
+                rejection: Rejection::Parse(error),
+            }),
+        }
+    }
 }
 
-struct NamedAssets {
-    pub character: Handle<Image>,
-    pub fire: Handle<Image>,
-    pub rock: Handle<Image>,
-    pub font: Handle<Font>,
+// Only the player's own spells, NPCs get their spells wrong in silence
+fn show_spell_rejections(
+    mut rejections: EventReader<SpellRejected>,
+    players: Query<(), With<HumanController>>,
+    mut query: Query<(&InputText, &mut Text)>,
+) {
+    let Some(rejected) = rejections
+        .read()
+        .filter(|rejected| rejected.caster.is_some_and(|caster| players.contains(caster)))
+        .last()
+    else {
+        return;
+    };
+
+    for (_input, mut text) in &mut query {
+        text.sections[1].value = format!("\n{}", rejected.rejection);
+    }
 }
 
-struct ConstructedAssets {
-    pub character_atlas: Handle<TextureAtlas>,
-    pub fire_atlas: Handle<TextureAtlas>,
-    pub rock_atlas: Handle<TextureAtlas>,
-    pub text_style: TextStyle,
+fn show_fluency_gains(
+    mut gains: EventReader<FluencyGained>,
+    players: Query<(), With<HumanController>>,
+    mut query: Query<(&InputText, &mut Text)>,
+) {
+    let Some(gain) = gains
+        .read()
+        .filter(|gain| players.contains(gain.caster))
+        .last()
+    else {
+        return;
+    };
+
+    for (_input, mut text) in &mut query {
+        text.sections[1].value = format!("\nYou can now cast {} spells", gain.dialect);
+    }
 }
 
-impl EntityFactory {
-    fn new(named_assets: NamedAssets, mut texture_assets: ResMut<Assets<TextureAtlas>>) -> Self {
-        let character_atlas = TextureAtlas::from_grid(
-            named_assets.character.clone(),
-            Vec2::new(128.0, 128.0),
-            24,
-            8,
-            None,
-            None,
-        );
-
-        let character_atlas_handle = texture_assets.add(character_atlas);
-
-        let fire_atlas = TextureAtlas::from_grid(
-            named_assets.fire.clone(),
-            Vec2::new(32.0, 32.0),
-            3,
-            1,
-            None,
-            None,
-        );
-
-        let fire_atlas_handle = texture_assets.add(fire_atlas);
-
-        let rock_atlas = TextureAtlas::from_grid(
-            named_assets.rock.clone(),
-            Vec2::new(32.0, 32.0),
-            5,
-            1,
-            None,
-            None,
-        );
-
-        let rock_atlas_handle = texture_assets.add(rock_atlas);
-
-        let constructed_assets = ConstructedAssets {
-            character_atlas: character_atlas_handle,
-            rock_atlas: rock_atlas_handle.clone(),
-            fire_atlas: fire_atlas_handle,
-            text_style: TextStyle {
-                font: named_assets.font.clone(),
-                font_size: 15.0,
-                color: Color::RED,
-            },
-        };
+fn show_cast_history(
+    history: Res<CastHistory>,
+    mut query: Query<&mut Text, With<CastHistoryPanel>>,
+) {
+    if !history.is_changed() {
+        return;
+    }
 
-        EntityFactory { constructed_assets }
+    let lines = history
+        .recent(CAST_HISTORY_SHOWN)
+        .map(|cast| {
+            let targets = cast
+                .targets
+                .iter()
+                .map(|(_, entities)| entities.len())
+                .sum::<usize>();
+            let source = match &cast.translation {
+                Some(translation) => format!("{} ({})", cast.source, translation),
+                None => cast.source.clone(),
+            };
+            format!(
+                "[{:6.1}s] {} -> {} ({} targets)",
+                cast.at.as_secs_f32(),
+                source,
+                cast.outcome,
+                targets
+            )
+        })
+        .collect::<Vec<_>>();
+
+    for mut text in &mut query {
+        text.sections[0].value = lines.join("\n");
     }
+}
 
-    fn make_human(&self) -> (HumanAgentBundle, Text2dBundle) {
-        (
-            new_human_agent_bundle(
-                self.constructed_assets.character_atlas.clone(),
-                SKELETON.clone(),
-            ),
-            make_speech_bubble(self.constructed_assets.text_style.clone()),
-        )
+fn export_cast_history(kbd: Res<Input<KeyCode>>, history: Res<CastHistory>) {
+    if !kbd.just_pressed(KeyCode::F9) {
+        return;
     }
 
-    fn make_ai(&self) -> (AiAgentBundle, Text2dBundle) {
-        (
-            new_ai_agent_bundle(
-                self.constructed_assets.character_atlas.clone(),
-                SKELETON.clone(),
-            ),
-            make_speech_bubble(self.constructed_assets.text_style.clone()),
-        )
+    match history.export(CAST_HISTORY_EXPORT) {
+        Ok(()) => info!("Exported cast history to {}", CAST_HISTORY_EXPORT),
+        Err(error) => warn!("{}", error),
     }
+}
 
-    fn make_rock(&self) -> MatterBlobBundleBundle {
-        new_matter_blob_bundle(self.constructed_assets.rock_atlas.clone(), SKELETON.clone())
+// Nothing saved yet just means the player is new to spellcasting
+fn load_player_fluency() -> (Fluency, Practice) {
+    match load_fluency(PLAYER_FLUENCY) {
+        Ok(saved) => saved,
+        Err(FluencyError::Io(error)) if error.kind() == std::io::ErrorKind::NotFound => {
+            (Fluency(Dialect::Apprentice), Practice::default())
+        }
+        Err(error) => {
+            warn!("{}", error);
+            (Fluency(Dialect::Apprentice), Practice::default())
+        }
     }
+}
 
-    fn make_fire(&self) -> MatterBlobBundleBundle {
-        new_matter_blob_bundle(self.constructed_assets.fire_atlas.clone(), SKELETON.clone())
+fn save_player_fluency(
+    players: Query<(Ref<Fluency>, &Practice), (With<HumanController>, Changed<Practice>)>,
+) {
+    for (fluency, practice) in &players {
+        if fluency.is_added() {
+            continue;
+        }
+
+        if let Err(error) = save_fluency(PLAYER_FLUENCY, *fluency, *practice) {
+            warn!("{}", error);
+        }
+    }
+}
+
+// No spellbook yet just means the player hasn't defined any words
+fn load_player_spellbook() -> Spellbook {
+    match Spellbook::load(PLAYER_SPELLBOOK) {
+        Ok(spellbook) => spellbook,
+        Err(SpellbookError::Io(error)) if error.kind() == std::io::ErrorKind::NotFound => {
+            Spellbook::default()
+        }
+        Err(error) => {
+            warn!("{}", error);
+            Spellbook::default()
+        }
     }
 }
 
@@ -308,12 +416,16 @@ fn setup(
     let character_handle = asset_server.load("skeleton_0.png");
     let fire_handle = asset_server.load("fireball.png");
     let rock_handle = asset_server.load("rock.png");
+    let items_handle = asset_server.load("Spellites.png");
+    let trees_handle = asset_server.load("128x128 trees.png");
     let font = asset_server.load("fonts/FiraMono-Medium.ttf");
 
     let assets = NamedAssets {
         character: character_handle.clone(),
         fire: fire_handle.clone(),
         rock: rock_handle.clone(),
+        items: items_handle.clone(),
+        trees: trees_handle.clone(),
         font: font.clone(),
     };
 
@@ -321,30 +433,67 @@ fn setup(
 
     let (human_bundle, text_bubble) = entity_factory.make_human();
 
-    commands.spawn(human_bundle).with_children(|parent| {
-        parent.spawn(text_bubble);
-    });
+    commands
+        .spawn(human_bundle)
+        .insert(load_player_spellbook())
+        .insert(load_player_fluency())
+        .with_children(|parent| {
+            parent.spawn(text_bubble);
+        });
+
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new(
+                "",
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 30.0,
+                    ..default()
+                },
+            ),
+            TextSection::new(
+                "",
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 20.0,
+                    color: Color::RED,
+                },
+            ),
+        ])
+        .with_text_alignment(TextAlignment::Center)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.0),
+            left: Val::Px(5.0),
+            ..default()
+        }),
+        InputText,
+    ));
 
     commands.spawn((
         TextBundle::from_section(
             "",
             TextStyle {
                 font: font.clone(),
-                font_size: 30.0,
-                ..default()
+                font_size: 16.0,
+                color: Color::rgb(0.8, 0.8, 0.8),
             },
         )
-        .with_text_alignment(TextAlignment::Center)
         .with_style(Style {
             position_type: PositionType::Absolute,
-            bottom: Val::Px(5.0),
-            left: Val::Px(5.0),
+            top: Val::Px(5.0),
+            right: Val::Px(5.0),
             ..default()
         }),
-        InputText,
+        CastHistoryPanel,
     ));
 
-    commands.spawn(create_spell());
+    // Loose rocks for lift and compress to work with
+    for position in [Vec2::new(200.0, 100.0), Vec2::new(260.0, 140.0), Vec2::new(230.0, 40.0)] {
+        let mut rock = entity_factory.make_rock();
+        rock.0.transform.translation = position.extend(10.0);
+        commands.spawn(rock);
+    }
 
     commands.insert_resource(OracleReaderConfig {
         timer: Timer::new(Duration::from_secs(1), TimerMode::Repeating),
@@ -355,24 +504,117 @@ fn setup(
     game.game_state = GameState::Playing;
 }
 
+// Nothing saved yet just means no spell has marked the ground
+fn load_terrain_alterations() -> TerrainAlterations {
+    match TerrainAlterations::load(TERRAIN_ALTERATIONS) {
+        Ok(alterations) => alterations,
+        Err(TerrainAlterationsError::Io(error)) if error.kind() == std::io::ErrorKind::NotFound => {
+            TerrainAlterations::default()
+        }
+        Err(error) => {
+            warn!("{}", error);
+            TerrainAlterations::default()
+        }
+    }
+}
+
+fn save_terrain_alterations(alterations: Res<TerrainAlterations>) {
+    if alterations.is_added() || !alterations.is_changed() {
+        return;
+    }
+
+    if let Err(error) = alterations.save(TERRAIN_ALTERATIONS) {
+        warn!("{}", error);
+    }
+}
+
 fn main() {
     App::new()
         .init_resource::<Game>()
+        .init_resource::<PendingTranslations>()
+        .init_resource::<CastHistory>()
+        .insert_resource(load_terrain_alterations())
         .add_event::<Shout>()
         .add_event::<CompletionCallback>()
+        .add_event::<Incantation>()
+        .add_event::<SpellRejected>()
+        .add_event::<SpellFinished>()
+        .add_event::<SpellCast>()
+        .add_event::<CounterAttempt>()
+        .add_event::<FluencyGained>()
+        .add_event::<ThermalReaction>()
+        .add_event::<Landed>()
+        .add_event::<Damaged>()
         .add_systems(Startup, setup)
         .add_systems(
             Update,
             (
                 animate_sprite,
-                update_spell,
+                update_spell.after(control_player),
+                interrupt_spells.after(apply_impact_damage).before(update_spell),
+                (
+                    tick_cooldowns,
+                    expire_lifetimes,
+                    regenerate_mana,
+                    counter_threats.before(update_spell),
+                ),
                 read_oracle,
+                receive_translations.after(read_oracle),
                 move_agent,
                 tick_ai,
-                (text_input, control_player, toggle_text_input),
-                handle_mouse,
+                (
+                    text_input,
+                    control_player,
+                    toggle_text_input,
+                    export_cast_history,
+                ),
+                cast_incantations.after(text_input).before(update_spell),
+                (
+                    show_spell_rejections
+                        .after(cast_incantations)
+                        .after(receive_translations),
+                    (record_casts, show_cast_history)
+                        .chain()
+                        .after(update_spell),
+                    (practise, (show_fluency_gains, save_player_fluency))
+                        .chain()
+                        .after(update_spell),
+                ),
+                (handle_mouse, draw_selection).chain(),
                 move_camera,
                 animate_blob,
+                (move_projectiles, touch_fire).chain(),
+                collect_pickups,
+                apply_knockback,
+                (
+                    fall_lifted,
+                    (apply_impact_damage, crater_ground),
+                    save_terrain_alterations,
+                )
+                    .chain(),
+                (
+                    spread_temperature,
+                    exchange_ground_heat,
+                    spread_tile_temperature,
+                    react_to_temperature,
+                    catch_fire,
+                )
+                    .chain(),
+                (
+                    expire_status::<Burning>,
+                    expire_status::<Levitating>,
+                    expire_status::<Frozen>,
+                    expire_status::<Compressed>,
+                    burn.before(interrupt_spells),
+                    levitate.after(fall_lifted),
+                    freeze
+                        .after(spread_tile_temperature)
+                        .before(react_to_temperature),
+                    squeeze
+                        .after(control_player)
+                        .after(tick_ai)
+                        .before(move_agent),
+                ),
             ),
         )
         .add_plugins(
@@ -389,5 +631,6 @@ fn main() {
         )
         .add_plugins(TilemapPlugin)
         .add_plugins(TiledMapPlugin)
+        .add_plugins(InteractionsPlugin)
         .run();
 }
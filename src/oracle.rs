@@ -63,10 +63,10 @@ pub fn read_oracle(
 
     if config.timer.finished() {
         if let Some(messages) = game.oracle.get_messages() {
-            completion_handler.send(CompletionCallback {
-                id: messages[0].0,
-                message: messages[0].1.clone(),
-            });
+            // Skeletons and spell translations share the queue, so nothing can be dropped
+            for (id, message) in messages {
+                completion_handler.send(CompletionCallback { id, message });
+            }
         }
     }
 }
@@ -0,0 +1,572 @@
+use std::time::Duration;
+
+use bevy::ecs::entity::Entity;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    focus::SpellTarget,
+    is_reversed,
+    parser::{Chain, Effect, Focus, Quality, Repeat, SpellAst},
+    SpellEffect, SpellState,
+};
+
+// The VM only ever moves in whole ticks so frame rate can't change what a spell does
+pub const TICK: Duration = Duration::from_millis(10);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Condition {
+    HasTarget,
+    // Everything in the target register has the quality
+    Is(Quality),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Instruction {
+    // Resolves the focus into the target register
+    Select(Focus),
+    // Starts the effect on everything in the target register
+    Apply(SpellEffect),
+    // Holds until every effect started so far has settled
+    Wait,
+    // Carries on from the given instruction when the condition doesn't hold
+    BranchUnless(Condition, usize),
+    // Marks the start of a clause that goes off more than once
+    Repeat(Repeat),
+    // Leaves the instructions from the given one up to here repeating alongside the rest of the
+    // spell, until the repeat is used up
+    Again(usize),
+}
+
+// An effect that has started and not yet settled, along with what it started on
+#[derive(Clone, Debug)]
+struct Running {
+    effect: SpellEffect,
+    target: SpellTarget,
+}
+
+// A repeated clause, going off again on its own schedule while the spell carries on
+#[derive(Clone, Debug)]
+struct Loop {
+    start: usize,
+    end: usize,
+    left: u32,
+    // Fixed ticks between runs, otherwise each run waits for the last one's effects to settle
+    every: Option<u32>,
+    // Ticks until the next run
+    next: u32,
+    // The effects of its last run that are still going
+    running: Vec<Running>,
+}
+
+fn repetitions(repeat: Repeat) -> u32 {
+    match repeat {
+        Repeat::Times(count) | Repeat::Seconds(count) => count,
+    }
+}
+
+// Effects chained with "and" start together, "then" waits for everything before it to settle,
+// however long that takes: lifted things to land and pushed ones to stop
+pub fn compile(ast: &SpellAst) -> Vec<Instruction> {
+    let mut program = Vec::new();
+
+    for (index, chained) in ast.effects.iter().enumerate() {
+        if index > 0 && chained.chain == Chain::Then {
+            program.push(Instruction::Wait);
+        }
+
+        if chained
+            .repeat
+            .is_some_and(|repeat| repetitions(repeat) == 0)
+        {
+            continue;
+        }
+
+        // Patched to the end of the clause once it's written
+        let guard = chained.guard.map(|guard| {
+            program.push(Instruction::Select(guard.focus));
+            program.push(Instruction::BranchUnless(Condition::Is(guard.quality), 0));
+            (program.len() - 1, guard.quality)
+        });
+
+        if let Some(repeat) = chained.repeat {
+            program.push(Instruction::Repeat(repeat));
+        }
+        let start = program.len();
+
+        let (effect, focus) = lower(&chained.effect);
+        let skip = program.len() + 3;
+
+        program.push(Instruction::Select(focus));
+        program.push(Instruction::BranchUnless(Condition::HasTarget, skip));
+        program.push(Instruction::Apply(effect));
+
+        if chained.repeat.is_some() {
+            program.push(Instruction::Again(start));
+        }
+
+        if let Some((branch, quality)) = guard {
+            program[branch] = Instruction::BranchUnless(Condition::Is(quality), program.len());
+        }
+    }
+
+    program.push(Instruction::Wait);
+    program
+}
+
+fn lower(effect: &Effect) -> (SpellEffect, Focus) {
+    match effect {
+        Effect::Lift(focus) => (SpellEffect::Lift, *focus),
+        Effect::Heat(focus, modifiers) => (
+            SpellEffect::Heat {
+                reverse: is_reversed(modifiers),
+            },
+            *focus,
+        ),
+        Effect::Compress(focus, modifiers) => (
+            SpellEffect::Compress {
+                reverse: is_reversed(modifiers),
+            },
+            *focus,
+        ),
+        Effect::Transform(focus, shape) => (SpellEffect::Transform(*shape), *focus),
+        Effect::Push(focus, direction) => (SpellEffect::Push(*direction), *focus),
+        Effect::Dispel(focus) => (SpellEffect::Dispel, *focus),
+    }
+}
+
+fn ticks(duration: Duration) -> u32 {
+    duration.as_millis().div_ceil(TICK.as_millis()) as u32
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Vm {
+    program: Vec<Instruction>,
+    pc: usize,
+    target: SpellTarget,
+    // Whatever the last effect spawned, for "it"
+    it: Option<Entity>,
+    // Effects that haven't settled yet
+    running: Vec<Running>,
+    loops: Vec<Loop>,
+    // The repeat the instructions being run belong to, and how many effects were already
+    // running when it started
+    repeat: Option<(Repeat, usize)>,
+    // Frame time that hasn't made up a whole tick yet
+    carry: Duration,
+    elapsed: u64,
+}
+
+impl Vm {
+    pub fn new(program: Vec<Instruction>) -> Self {
+        Vm {
+            program,
+            ..Default::default()
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        self.pc >= self.program.len() && self.running.is_empty() && self.loops.is_empty()
+    }
+
+    // Runs as many whole ticks as the frame covers, `resolve` turns a focus into targets,
+    // `apply` starts an effect on them and `check` tests them for a guard's quality.
+    // `settled` says whether an effect has finished with its targets, it's only asked once a
+    // frame since effects only take hold on the world between frames.
+    pub fn advance(
+        &mut self,
+        delta: Duration,
+        mut resolve: impl FnMut(Focus, Option<Entity>) -> SpellTarget,
+        mut apply: impl FnMut(&SpellEffect, &SpellTarget) -> Option<SpellState>,
+        mut check: impl FnMut(Quality, &SpellTarget) -> bool,
+        mut settled: impl FnMut(&SpellEffect, &SpellTarget) -> bool,
+    ) {
+        let mut still_going = |running: &Running| !settled(&running.effect, &running.target);
+        self.running.retain(&mut still_going);
+        for repeating in &mut self.loops {
+            repeating.running.retain(&mut still_going);
+        }
+
+        self.carry += delta;
+
+        while self.carry >= TICK && !self.finished() {
+            self.carry -= TICK;
+            self.tick(&mut resolve, &mut apply, &mut check);
+        }
+    }
+
+    fn tick(
+        &mut self,
+        resolve: &mut impl FnMut(Focus, Option<Entity>) -> SpellTarget,
+        apply: &mut impl FnMut(&SpellEffect, &SpellTarget) -> Option<SpellState>,
+        check: &mut impl FnMut(Quality, &SpellTarget) -> bool,
+    ) {
+        self.elapsed += 1;
+
+        self.repeat_loops(resolve, apply, check);
+
+        while let Some(instruction) = self.program.get(self.pc).copied() {
+            match instruction {
+                Instruction::Wait => {
+                    if !self.running.is_empty() || !self.loops.is_empty() {
+                        return;
+                    }
+                }
+                Instruction::Repeat(repeat) => {
+                    self.repeat = Some((repeat, self.running.len()));
+                }
+                Instruction::Again(start) => {
+                    if let Some((repeat, from)) = self.repeat.take() {
+                        self.loop_from(start, repeat, from);
+                    }
+                }
+                instruction => {
+                    if let Some(to) = self.execute(instruction, resolve, apply, check) {
+                        self.pc = to;
+                        continue;
+                    }
+                }
+            }
+
+            self.pc += 1;
+        }
+    }
+
+    // Keeps the clause that just ran once going, if it has repetitions left. The effects it
+    // started from `from` on are its own to wait for.
+    fn loop_from(&mut self, start: usize, repeat: Repeat, from: usize) {
+        let left = repetitions(repeat).saturating_sub(1);
+        if left == 0 {
+            return;
+        }
+
+        let every = match repeat {
+            Repeat::Times(_) => None,
+            // Once a second, whatever the effect, so the cost says how often it goes off
+            Repeat::Seconds(_) => Some(ticks(Duration::from_secs(1))),
+        };
+
+        self.loops.push(Loop {
+            start,
+            end: self.pc,
+            left,
+            every,
+            // At least a tick between runs, so effects that settle straight away can't spin
+            // within one tick
+            next: every.unwrap_or(1),
+            running: self.running.split_off(from),
+        });
+    }
+
+    fn repeat_loops(
+        &mut self,
+        resolve: &mut impl FnMut(Focus, Option<Entity>) -> SpellTarget,
+        apply: &mut impl FnMut(&SpellEffect, &SpellTarget) -> Option<SpellState>,
+        check: &mut impl FnMut(Quality, &SpellTarget) -> bool,
+    ) {
+        // The main run may be partway through using the target register
+        let target = std::mem::take(&mut self.target);
+
+        // Whatever the loops start is kept apart from the main run's effects
+        let running = std::mem::take(&mut self.running);
+
+        let mut loops = std::mem::take(&mut self.loops);
+        for repeating in &mut loops {
+            repeating.next = repeating.next.saturating_sub(1);
+            let due = repeating.every.is_some() || repeating.running.is_empty();
+            if repeating.next > 0 || !due {
+                continue;
+            }
+
+            let mut pc = repeating.start;
+            while pc < repeating.end {
+                match self.execute(self.program[pc], resolve, apply, check) {
+                    Some(to) => pc = to,
+                    None => pc += 1,
+                }
+            }
+
+            repeating.left -= 1;
+            repeating.next = repeating.every.unwrap_or(1);
+            repeating.running.append(&mut self.running);
+        }
+
+        // A used up loop's last effects still hold up the next wait
+        self.running = running;
+        for repeating in &mut loops {
+            if repeating.left == 0 {
+                self.running.append(&mut repeating.running);
+            }
+        }
+        loops.retain(|repeating| repeating.left > 0);
+        self.loops = loops;
+
+        self.target = target;
+    }
+
+    // Runs an instruction that doesn't hold the VM up, giving where to carry on from if it
+    // branches
+    fn execute(
+        &mut self,
+        instruction: Instruction,
+        resolve: &mut impl FnMut(Focus, Option<Entity>) -> SpellTarget,
+        apply: &mut impl FnMut(&SpellEffect, &SpellTarget) -> Option<SpellState>,
+        check: &mut impl FnMut(Quality, &SpellTarget) -> bool,
+    ) -> Option<usize> {
+        match instruction {
+            Instruction::Select(focus) => self.target = resolve(focus, self.it),
+            Instruction::Apply(effect) => match apply(&effect, &self.target) {
+                Some(SpellState::Finished) | None => {}
+                Some(state) => {
+                    if let SpellState::SpawnedEffect(spawned) = state {
+                        self.it = Some(spawned);
+                    }
+
+                    self.running.push(Running {
+                        effect,
+                        target: self.target.clone(),
+                    });
+                }
+            },
+            Instruction::BranchUnless(condition, to) => {
+                let holds = match condition {
+                    Condition::HasTarget => !self.target.entities.is_empty(),
+                    Condition::Is(quality) => check(quality, &self.target),
+                };
+
+                if !holds {
+                    return Some(to);
+                }
+            }
+            Instruction::Wait | Instruction::Repeat(_) | Instruction::Again(_) => {}
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::spell::{
+        mana::spell_cost,
+        parser::{parse_spell, Direction},
+    };
+
+    #[test]
+    fn waits_for_the_group_before_each_then() {
+        let ast =
+            parse_spell("heat me and lift me then compress it and heat it then lift me").unwrap();
+        let program = compile(&ast);
+
+        let waits = program
+            .iter()
+            .enumerate()
+            .filter(|(_, instruction)| **instruction == Instruction::Wait)
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        assert_eq!(waits, vec![6, 13, 17]);
+
+        let json = serde_json::to_string(&program).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Vec<Instruction>>(&json).unwrap(),
+            program
+        );
+    }
+
+    // How many frames a lift or push keeps its target moving in these tests
+    const MOVING_FRAMES: u32 = 20;
+
+    // Which effects started on what, and how many ticks the spell took overall
+    fn run(program: &[Instruction], frame: Duration) -> (Vec<(SpellEffect, Vec<Entity>)>, u64) {
+        let caster = Entity::from_raw(1);
+        let fire = Entity::from_raw(2);
+
+        let mut vm = Vm::new(program.to_vec());
+        let mut trace = Vec::new();
+        // Everything moving and the frames until it stops
+        let moving = RefCell::new(Vec::<(Entity, u32)>::new());
+
+        while !vm.finished() {
+            vm.advance(
+                frame,
+                |focus, it| SpellTarget {
+                    entities: match focus {
+                        Focus::Me => vec![caster],
+                        Focus::It => it.into_iter().collect(),
+                        _ => Vec::new(),
+                    },
+                },
+                |effect, target| {
+                    trace.push((*effect, target.entities.clone()));
+                    if matches!(effect, SpellEffect::Lift | SpellEffect::Push(_)) {
+                        let started = target
+                            .entities
+                            .iter()
+                            .map(|entity| (*entity, MOVING_FRAMES));
+                        moving.borrow_mut().extend(started);
+                    }
+                    match effect {
+                        SpellEffect::Heat { .. } => Some(SpellState::SpawnedEffect(fire)),
+                        _ => Some(SpellState::Active),
+                    }
+                },
+                // Only the fire is hot
+                |quality, target| quality == Quality::Hot && target.entities == vec![fire],
+                |_, target| {
+                    !moving
+                        .borrow()
+                        .iter()
+                        .any(|(entity, _)| target.entities.contains(entity))
+                },
+            );
+
+            moving.borrow_mut().retain_mut(|(_, frames)| {
+                *frames -= 1;
+                *frames > 0
+            });
+        }
+
+        (trace, vm.elapsed)
+    }
+
+    // Whether the spell lasted at least the given number of frames
+    fn lasted(elapsed: u64, frames: u32, frame: Duration) -> bool {
+        TICK * elapsed as u32 >= frame * frames
+    }
+
+    #[test]
+    fn runs_the_same_whatever_the_frame_rate() {
+        let ast = parse_spell("heat me and lift target then push it back").unwrap();
+        let program = compile(&ast);
+
+        let frame = Duration::from_millis(16);
+        let smooth = run(&program, frame);
+        assert_eq!(run(&program, frame), smooth);
+        assert_eq!(run(&program, Duration::from_millis(50)).0, smooth.0);
+
+        // Lift has nothing selected so it's skipped, push goes for the fire once the heat is done
+        // and the spell isn't over until the fire stops moving
+        let (trace, elapsed) = smooth;
+        assert_eq!(
+            trace,
+            vec![
+                (
+                    SpellEffect::Heat { reverse: false },
+                    vec![Entity::from_raw(1)]
+                ),
+                (
+                    SpellEffect::Push(Direction::Back),
+                    vec![Entity::from_raw(2)]
+                ),
+            ]
+        );
+        assert!(lasted(elapsed, MOVING_FRAMES, frame));
+    }
+
+    #[test]
+    fn then_waits_for_a_lift_to_land() {
+        let caster = Entity::from_raw(1);
+        let mut vm = Vm::new(compile(&parse_spell("lift me then heat me").unwrap()));
+        let started = RefCell::new(Vec::new());
+
+        let frame = |vm: &mut Vm, landed: bool| {
+            vm.advance(
+                Duration::from_millis(16),
+                |_, _| SpellTarget {
+                    entities: vec![caster],
+                },
+                |effect, _| {
+                    started.borrow_mut().push(*effect);
+                    Some(SpellState::Active)
+                },
+                |_, _| true,
+                |_, _| landed,
+            );
+        };
+
+        // Far longer than any lift would hold something up
+        for _ in 0..1000 {
+            frame(&mut vm, false);
+        }
+        assert_eq!(*started.borrow(), vec![SpellEffect::Lift]);
+
+        frame(&mut vm, true);
+        frame(&mut vm, true);
+        assert!(vm.finished());
+        assert_eq!(
+            *started.borrow(),
+            vec![SpellEffect::Lift, SpellEffect::Heat { reverse: false }]
+        );
+    }
+
+    #[test]
+    fn guards_skip_and_repeats_loop() {
+        let ast = parse_spell(
+            "if me is hot then heat me then heat me then if it is hot then lift me thrice",
+        )
+        .unwrap();
+        let frame = Duration::from_millis(16);
+        let (trace, elapsed) = run(&compile(&ast), frame);
+
+        let effects = trace.iter().map(|(effect, _)| *effect).collect::<Vec<_>>();
+        assert_eq!(
+            effects,
+            vec![
+                SpellEffect::Heat { reverse: false },
+                SpellEffect::Lift,
+                SpellEffect::Lift,
+                SpellEffect::Lift,
+            ]
+        );
+        // Each lift waits for the last one to land before going again
+        assert!(lasted(elapsed, 3 * MOVING_FRAMES, frame));
+
+        let ast = parse_spell("heat me for 3 seconds").unwrap();
+        let (trace, _) = run(&compile(&ast), Duration::from_millis(16));
+        assert_eq!(trace.len(), 3);
+    }
+
+    #[test]
+    fn timed_repeats_go_off_once_a_second() {
+        // Effects that are over straight away mustn't go off every tick for the price of one a
+        // second
+        for (timed, once) in [
+            ("dispel me for 3 seconds", "dispel me"),
+            ("lift me for 3 seconds", "lift me"),
+        ] {
+            let ast = parse_spell(timed).unwrap();
+            let (trace, _) = run(&compile(&ast), Duration::from_millis(16));
+            assert_eq!(trace.len(), 3, "{}", timed);
+            assert_eq!(
+                spell_cost(&ast),
+                3.0 * spell_cost(&parse_spell(once).unwrap())
+            );
+        }
+
+        let ast = parse_spell("heat me for 0 seconds").unwrap();
+        let (trace, elapsed) = run(&compile(&ast), Duration::from_millis(16));
+        assert_eq!(trace, Vec::new());
+        assert_eq!(elapsed, 1);
+        assert_eq!(spell_cost(&ast), 0.0);
+    }
+
+    #[test]
+    fn repeats_start_together_with_the_rest_of_their_group() {
+        let ast = parse_spell("lift me thrice and heat me then push it back").unwrap();
+        let (trace, _) = run(&compile(&ast), Duration::from_millis(16));
+
+        let effects = trace.iter().map(|(effect, _)| *effect).collect::<Vec<_>>();
+        assert_eq!(
+            effects,
+            vec![
+                SpellEffect::Lift,
+                SpellEffect::Heat { reverse: false },
+                SpellEffect::Lift,
+                SpellEffect::Lift,
+                SpellEffect::Push(Direction::Back),
+            ]
+        );
+    }
+}
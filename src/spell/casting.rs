@@ -0,0 +1,76 @@
+use std::{collections::HashMap, time::Duration};
+
+use bevy::{
+    ecs::{
+        component::Component,
+        event::EventReader,
+        system::{Query, Res},
+    },
+    time::{Time, Timer, TimerMode},
+};
+
+use crate::agent::Damaged;
+
+use super::{parser::SpellAst, Spell};
+
+// Bigger spells take longer to incant and longer before they can be cast again
+pub const CAST_TIME_PER_MANA: Duration = Duration::from_millis(20);
+pub const COOLDOWN_PER_MANA: Duration = Duration::from_millis(100);
+
+// Spells the caster has to wait on before casting them again, keyed by what the spell does
+// rather than how it was typed
+#[derive(Component, Default)]
+pub struct Cooldowns {
+    timers: HashMap<SpellAst, Timer>,
+}
+
+impl Cooldowns {
+    pub fn start(&mut self, ast: SpellAst, cooldown: Duration) {
+        self.timers
+            .insert(ast, Timer::new(cooldown, TimerMode::Once));
+    }
+
+    pub fn remaining(&self, ast: &SpellAst) -> Option<Duration> {
+        self.timers
+            .get(ast)
+            .filter(|timer| !timer.finished())
+            .map(|timer| timer.remaining())
+    }
+}
+
+pub fn tick_cooldowns(time: Res<Time>, mut query: Query<&mut Cooldowns>) {
+    for mut cooldowns in &mut query {
+        cooldowns.timers.retain(|_, timer| {
+            timer.tick(time.delta());
+            !timer.finished()
+        });
+    }
+}
+
+pub fn interrupt_spells(mut damaged: EventReader<Damaged>, mut spells: Query<&mut Spell>) {
+    for damage in damaged.read() {
+        for mut spell in &mut spells {
+            if spell.caster == Some(damage.entity) {
+                spell.interrupt();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::spell::parser::parse_spell;
+
+    #[test]
+    fn cooldowns_are_per_spell() {
+        let heat = parse_spell("heat target").unwrap();
+        let lift = parse_spell("lift target").unwrap();
+
+        let mut cooldowns = Cooldowns::default();
+        cooldowns.start(heat.clone(), Duration::from_secs(1));
+
+        assert_eq!(cooldowns.remaining(&heat), Some(Duration::from_secs(1)));
+        assert_eq!(cooldowns.remaining(&lift), None);
+    }
+}
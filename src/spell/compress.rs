@@ -0,0 +1,242 @@
+use std::{f32::consts::TAU, time::Duration};
+
+use bevy::{
+    asset::Handle,
+    ecs::{component::Component, entity::Entity, query::With, system::Commands, world::World},
+    hierarchy::despawn_with_children_recursive,
+    math::{Vec2, Vec3},
+    sprite::TextureAtlas,
+    time::{Timer, TimerMode},
+    transform::components::Transform,
+};
+
+use crate::agent::SKELETON;
+
+use super::{
+    interaction::{outcomes, EffectKind, ObjectKind},
+    lifetime::Lifetime,
+    new_matter_blob_bundle,
+    parser::Direction,
+    push::{knockback, Knockback},
+    shape::{place, Obstacle, Projectile},
+    temperature::{Temperature, AMBIENT},
+    BlobState,
+};
+
+// How far compress reaches to pull blobs together
+pub const COMPRESS_RADIUS: f32 = 128.0;
+
+// Compressed blobs at least this heavy are solid enough to stand as a wall segment
+pub const WALL_MASS: f32 = 4.0;
+
+const THROW_SPEED: f32 = 500.0;
+
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Mass(pub f32);
+
+impl Default for Mass {
+    fn default() -> Self {
+        Mass(1.0)
+    }
+}
+
+pub fn blob_scale(mass: f32) -> Vec3 {
+    Vec3::splat(2.0 * mass.max(0.0).sqrt())
+}
+
+pub fn compress_around(commands: &mut Commands, centre: Vec2) {
+    commands.add(move |world: &mut World| merge_blobs(world, centre));
+}
+
+pub fn scatter(commands: &mut Commands, entity: Entity, lifetime: Duration) {
+    commands.add(move |world: &mut World| scatter_blob(world, entity, lifetime));
+}
+
+// Compressed blobs are dense enough to fly like a thrown rock, anything else gets shoved and
+// slides to a stop
+pub fn push_or_throw(commands: &mut Commands, entity: Entity, direction: Direction, heading: Vec2) {
+    commands.add(move |world: &mut World| {
+        let knockback = knockback(world, direction, heading);
+        let Some(mut target) = world.get_entity_mut(entity) else {
+            return;
+        };
+
+        if target.get::<BlobState>() == Some(&BlobState::Compressed) {
+            target.remove::<Obstacle>().insert(Projectile {
+                velocity: knockback.velocity.normalize_or_zero() * THROW_SPEED,
+                lifetime: Timer::from_seconds(3.0, TimerMode::Once),
+            });
+        } else {
+            target.insert(knockback);
+        }
+    });
+}
+
+// Blobs the interaction table has its own plans for, like fire becoming a fireball, are left out
+fn merge_blobs(world: &mut World, centre: Vec2) {
+    let mut query =
+        world.query_filtered::<(Entity, &Transform, &Mass, Option<&ObjectKind>), With<BlobState>>();
+
+    let has_plans = |kind: Option<&ObjectKind>| {
+        kind.is_some_and(|kind| !outcomes(world, EffectKind::Compress, *kind).is_empty())
+    };
+
+    let blobs = query
+        .iter(world)
+        .filter(|(_, _, _, kind)| !has_plans(*kind))
+        .map(|(entity, transform, mass, _)| (entity, transform.translation.truncate(), mass.0))
+        .filter(|(_, position, _)| position.distance(centre) <= COMPRESS_RADIUS)
+        .collect::<Vec<(Entity, Vec2, f32)>>();
+
+    // The heaviest blob soaks up the rest, or the one nearest the centre if they weigh the same
+    let Some((survivor, _, _)) = blobs
+        .iter()
+        .copied()
+        .max_by(|(_, a, a_mass), (_, b, b_mass)| {
+            a_mass
+                .total_cmp(b_mass)
+                .then(b.distance(centre).total_cmp(&a.distance(centre)))
+        })
+    else {
+        return;
+    };
+
+    let total = blobs.iter().map(|(_, _, mass)| mass).sum::<f32>();
+    let centroid = blobs
+        .iter()
+        .map(|(_, position, mass)| *position * *mass)
+        .sum::<Vec2>()
+        / total;
+
+    for (entity, _, _) in &blobs {
+        if *entity != survivor {
+            despawn_with_children_recursive(world, *entity);
+        }
+    }
+
+    // Whatever compress packs together is solid enough to stay put
+    let mut survivor = world.entity_mut(survivor);
+    survivor
+        .insert((Mass(total), BlobState::Compressed))
+        .remove::<Lifetime>();
+
+    if let Some(mut transform) = survivor.get_mut::<Transform>() {
+        place(&mut transform, centroid);
+        transform.scale = blob_scale(total);
+    }
+
+    if total >= WALL_MASS {
+        survivor.insert(Obstacle {
+            radius: 16.0 * blob_scale(total).x,
+        });
+    }
+}
+
+fn scatter_blob(world: &mut World, entity: Entity, lifetime: Duration) {
+    let Some(blob) = world.get_entity(entity) else {
+        return;
+    };
+
+    if !blob.contains::<BlobState>() {
+        return;
+    }
+
+    let (Some(mass), Some(transform), Some(atlas)) = (
+        blob.get::<Mass>().copied(),
+        blob.get::<Transform>().copied(),
+        blob.get::<Handle<TextureAtlas>>().cloned(),
+    ) else {
+        return;
+    };
+
+    let degrees = blob.get::<Temperature>().map_or(AMBIENT, |t| t.degrees);
+    let kind = blob
+        .get::<ObjectKind>()
+        .copied()
+        .unwrap_or(ObjectKind::Rock);
+
+    let pieces = mass.0.floor().max(1.0) as usize;
+    if pieces < 2 {
+        return;
+    }
+
+    // Status overlays and lift shadows go with it
+    despawn_with_children_recursive(world, entity);
+
+    let position = transform.translation.truncate();
+    for piece in 0..pieces {
+        let heading = Vec2::from_angle(TAU * piece as f32 / pieces as f32);
+
+        let mut bundle = new_matter_blob_bundle(atlas.clone(), SKELETON.clone(), degrees, kind);
+        place(&mut bundle.0.transform, position);
+        bundle.5 = Mass(mass.0 / pieces as f32);
+
+        world.spawn((bundle, Knockback::new(heading), Lifetime::new(lifetime)));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::spell::interaction::{InteractionTable, Interactions};
+    use bevy::asset::Assets;
+
+    #[test]
+    fn merges_nearby_blobs_into_the_heaviest() {
+        let mut world = World::new();
+
+        let spawn = |world: &mut World, x: f32, mass: f32| {
+            world
+                .spawn((
+                    Transform::from_xyz(x, 0.0, 0.0),
+                    Mass(mass),
+                    BlobState::Floating,
+                ))
+                .id()
+        };
+
+        let light = spawn(&mut world, 0.0, 1.0);
+        let heavy = spawn(&mut world, 60.0, 3.0);
+        let far_away = spawn(&mut world, 1000.0, 1.0);
+
+        merge_blobs(&mut world, Vec2::ZERO);
+
+        assert!(world.get_entity(light).is_none());
+        assert_eq!(world.get::<Mass>(heavy), Some(&Mass(4.0)));
+        assert_eq!(world.get::<BlobState>(heavy), Some(&BlobState::Compressed));
+        assert_eq!(world.get::<Transform>(heavy).unwrap().translation.x, 45.0);
+        assert!(world.get::<Obstacle>(heavy).is_some());
+        assert_eq!(world.get::<Mass>(far_away), Some(&Mass(1.0)));
+    }
+
+    #[test]
+    fn leaves_fire_for_the_interaction_table() {
+        let table: InteractionTable =
+            ron::de::from_str(include_str!("../../assets/elements.interactions.ron")).unwrap();
+        let mut tables = Assets::<InteractionTable>::default();
+        let handle = tables.add(table);
+
+        let mut world = World::new();
+        world.insert_resource(tables);
+        world.insert_resource(Interactions(handle));
+
+        let spawn = |world: &mut World, x: f32, kind: ObjectKind| {
+            world
+                .spawn((
+                    Transform::from_xyz(x, 0.0, 0.0),
+                    Mass(1.0),
+                    BlobState::Floating,
+                    kind,
+                ))
+                .id()
+        };
+
+        let rock = spawn(&mut world, 0.0, ObjectKind::Rock);
+        let fire = spawn(&mut world, 20.0, ObjectKind::Fire);
+
+        merge_blobs(&mut world, Vec2::ZERO);
+
+        assert_eq!(world.get::<BlobState>(rock), Some(&BlobState::Compressed));
+        assert_eq!(world.get::<BlobState>(fire), Some(&BlobState::Floating));
+    }
+}
@@ -0,0 +1,183 @@
+use bevy::{
+    ecs::{
+        entity::Entity,
+        event::Event,
+        query::With,
+        system::{Commands, Query},
+        world::World,
+    },
+    transform::components::Transform,
+};
+
+use crate::agent::npc::AiController;
+
+use super::{
+    casting::Cooldowns,
+    focus::{SelectedTarget, AROUND_RADIUS},
+    mana::{spell_cost, Mana},
+    parser::{parse_spell_in, Dialect, Effect, SpellAst},
+    status::cleanse,
+    Fluency, Spell, SpellState,
+};
+
+// Spells made only of dispels take this share of the usual cast time, so they can land while
+// the spell they're aimed at is still being incanted
+pub const COUNTER_CAST_TIME: f32 = 0.25;
+
+// The most an NPC will repeat a dispel to outweigh a spell
+const MAX_COUNTER_REPEATS: u32 = 5;
+
+type Defender<'a> = (
+    Entity,
+    &'a Transform,
+    &'a Mana,
+    &'a Fluency,
+    Option<&'a Cooldowns>,
+);
+
+// A dispel that caught its target in the middle of casting
+#[derive(Event, Clone, Copy, Debug)]
+pub struct CounterAttempt {
+    pub spell: Entity,
+    pub by: Option<Entity>,
+    // The mana spent on the dispel and on the spell it was aimed at
+    pub power: f32,
+    pub resistance: f32,
+}
+
+impl CounterAttempt {
+    pub fn succeeded(&self) -> bool {
+        self.power >= self.resistance
+    }
+}
+
+pub fn is_counter_spell(ast: &SpellAst) -> bool {
+    ast.effects
+        .iter()
+        .all(|chained| matches!(chained.effect, Effect::Dispel(_)))
+}
+
+// Breaks whatever the entity is still casting and lifts its statuses, as long as no more mana
+// went into them than into the dispel
+pub fn dispel(commands: &mut Commands, entity: Entity, power: f32, by: Option<Entity>) {
+    commands.add(move |world: &mut World| {
+        let mut spells = world.query::<(Entity, &mut Spell)>();
+        let mut attempts = spells
+            .iter_mut(world)
+            .filter(|(_, spell)| spell.caster == Some(entity) && spell.state == SpellState::Casting)
+            .map(|(spell_entity, mut spell)| {
+                let attempt = CounterAttempt {
+                    spell: spell_entity,
+                    by,
+                    power,
+                    resistance: spell.cost,
+                };
+                if attempt.succeeded() {
+                    spell.interrupt();
+                }
+                attempt
+            })
+            .collect::<Vec<_>>();
+
+        attempts.sort_by_key(|attempt| attempt.spell);
+        for attempt in attempts {
+            world.send_event(attempt);
+        }
+
+        cleanse(world, entity, power);
+    });
+}
+
+// The cheapest dispel on the target that outweighs a spell the given cost
+fn counter_spell(resistance: f32, dialect: Dialect) -> Option<(String, SpellAst)> {
+    (1..=MAX_COUNTER_REPEATS).find_map(|times| {
+        let source = match times {
+            1 => "dispel target".to_string(),
+            _ => format!("dispel target {} times", times),
+        };
+        let ast = parse_spell_in(&source, dialect).ok()?;
+
+        (spell_cost(&ast) >= resistance).then_some((source, ast))
+    })
+}
+
+// NPCs answer the biggest spell being cast at them with a dispel, when they can afford one that
+// would win
+pub fn counter_threats(
+    mut commands: Commands,
+    npcs: Query<Defender, With<AiController>>,
+    spells: Query<&Spell>,
+    casters: Query<(&Transform, Option<&SelectedTarget>)>,
+) {
+    for (npc, transform, mana, fluency, cooldowns) in &npcs {
+        // Still busy with the last one
+        if spells.iter().any(|spell| spell.caster == Some(npc)) {
+            continue;
+        }
+
+        let position = transform.translation.truncate();
+
+        let threat = spells
+            .iter()
+            .filter(|spell| spell.state == SpellState::Casting)
+            .filter_map(|spell| {
+                let caster = spell.caster.filter(|caster| *caster != npc)?;
+                let (caster_transform, selection) = casters.get(caster).ok()?;
+
+                let distance = caster_transform.translation.truncate().distance(position);
+                let aimed = selection.is_some_and(|selection| selection.0 == npc)
+                    || distance <= AROUND_RADIUS;
+
+                aimed.then_some((caster, spell.cost))
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b));
+
+        let Some((caster, resistance)) = threat else {
+            continue;
+        };
+
+        let Some((source, ast)) = counter_spell(resistance, fluency.0) else {
+            continue;
+        };
+
+        let cooling_down = cooldowns.is_some_and(|cooldowns| cooldowns.remaining(&ast).is_some());
+        if cooling_down || spell_cost(&ast) > mana.current {
+            continue;
+        }
+
+        commands.entity(npc).insert(SelectedTarget(caster));
+        commands.spawn(Spell::from(ast).with_source(&source).cast_by(npc));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::spell::parser::parse_spell;
+
+    #[test]
+    fn counters_with_just_enough_mana() {
+        let (source, ast) = counter_spell(35.0, Dialect::Apprentice).unwrap();
+        assert_eq!(source, "dispel target 3 times");
+        assert_eq!(spell_cost(&ast), 45.0);
+        assert!(is_counter_spell(&ast));
+
+        assert_eq!(
+            counter_spell(10.0, Dialect::Apprentice).unwrap().0,
+            "dispel target"
+        );
+        assert_eq!(counter_spell(500.0, Dialect::Apprentice), None);
+        assert!(!is_counter_spell(
+            &parse_spell("dispel target and lift me").unwrap()
+        ));
+
+        let attempt = |power| CounterAttempt {
+            spell: Entity::from_raw(1),
+            by: None,
+            power,
+            resistance: 30.0,
+        };
+        assert!(attempt(30.0).succeeded());
+        assert!(!attempt(29.0).succeeded());
+    }
+}
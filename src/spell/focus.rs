@@ -0,0 +1,169 @@
+use bevy::{
+    ecs::{component::Component, entity::Entity},
+    math::Vec2,
+};
+
+use super::{
+    parser::{Focus, Quality},
+    temperature::{COLD, HOT},
+};
+
+// How far around_me and around_target reach from the entity they're centred on
+pub const AROUND_RADIUS: f32 = 160.0;
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SelectedTarget(pub Entity);
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SpellTarget {
+    pub entities: Vec<Entity>,
+}
+
+pub struct FocusContext<'a> {
+    pub caster: Option<Entity>,
+    pub selected: Option<Entity>,
+    pub positions: &'a [(Entity, Vec2)],
+    pub temperatures: &'a [(Entity, f32)],
+    // Everything below full health
+    pub hurt: &'a [Entity],
+    // Everything still in the air, being knocked back or flying
+    pub moving: &'a [Entity],
+}
+
+impl FocusContext<'_> {
+    pub fn resolve(&self, focus: Focus, it: Option<Entity>) -> SpellTarget {
+        let entities = match focus {
+            Focus::Me => self.caster.into_iter().collect(),
+            Focus::Target => self.selected.into_iter().collect(),
+            Focus::It => it.into_iter().collect(),
+            Focus::AroundMe => self.around(self.caster),
+            Focus::AroundTarget => self.around(self.selected),
+        };
+
+        SpellTarget { entities }
+    }
+
+    pub fn position(&self, entity: Entity) -> Option<Vec2> {
+        self.positions
+            .iter()
+            .find(|(candidate, _)| *candidate == entity)
+            .map(|(_, position)| *position)
+    }
+
+    // Guards hold when every entity in the focus has the quality, and there is at least one
+    pub fn holds(&self, quality: Quality, target: &SpellTarget) -> bool {
+        !target.entities.is_empty()
+            && target
+                .entities
+                .iter()
+                .all(|entity| self.is(quality, *entity))
+    }
+
+    // An effect has run its course once nothing it was started on is still moving
+    pub fn settled(&self, target: &SpellTarget) -> bool {
+        !target
+            .entities
+            .iter()
+            .any(|entity| self.moving.contains(entity))
+    }
+
+    fn is(&self, quality: Quality, entity: Entity) -> bool {
+        let temperature = || {
+            self.temperatures
+                .iter()
+                .find(|(candidate, _)| *candidate == entity)
+                .map(|(_, degrees)| *degrees)
+        };
+        let distance = || {
+            let origin = self.position(self.caster?)?;
+            Some(self.position(entity)?.distance(origin))
+        };
+
+        match quality {
+            Quality::Hot => temperature().is_some_and(|degrees| degrees >= HOT),
+            Quality::Cold => temperature().is_some_and(|degrees| degrees <= COLD),
+            Quality::Hurt => self.hurt.contains(&entity),
+            Quality::Near => distance().is_some_and(|distance| distance <= AROUND_RADIUS),
+            Quality::Far => distance().is_some_and(|distance| distance > AROUND_RADIUS),
+        }
+    }
+
+    fn around(&self, centre: Option<Entity>) -> Vec<Entity> {
+        let Some(centre) = centre else {
+            return Vec::new();
+        };
+
+        let Some(origin) = self.position(centre) else {
+            return Vec::new();
+        };
+
+        self.positions
+            .iter()
+            .filter(|(entity, position)| {
+                *entity != centre && position.distance(origin) <= AROUND_RADIUS
+            })
+            .map(|(entity, _)| *entity)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn resolves_keywords_against_the_caster_and_selection() {
+        let caster = Entity::from_raw(1);
+        let selected = Entity::from_raw(2);
+        let near_caster = Entity::from_raw(3);
+        let far_away = Entity::from_raw(4);
+
+        let positions = [
+            (caster, Vec2::new(0.0, 0.0)),
+            (selected, Vec2::new(500.0, 0.0)),
+            (near_caster, Vec2::new(50.0, 50.0)),
+            (far_away, Vec2::new(1000.0, 1000.0)),
+        ];
+
+        let temperatures = [(selected, 400.0), (near_caster, -20.0)];
+        let hurt = [caster];
+
+        let context = FocusContext {
+            caster: Some(caster),
+            selected: Some(selected),
+            positions: &positions,
+            temperatures: &temperatures,
+            hurt: &hurt,
+            moving: &[caster],
+        };
+
+        assert_eq!(context.resolve(Focus::Me, None).entities, vec![caster]);
+        assert!(!context.settled(&context.resolve(Focus::Me, None)));
+        assert!(context.settled(&context.resolve(Focus::Target, None)));
+        assert_eq!(
+            context.resolve(Focus::Target, None).entities,
+            vec![selected]
+        );
+        assert_eq!(
+            context.resolve(Focus::AroundMe, None).entities,
+            vec![near_caster]
+        );
+        assert!(context
+            .resolve(Focus::AroundTarget, None)
+            .entities
+            .is_empty());
+        assert!(context.resolve(Focus::It, None).entities.is_empty());
+        assert_eq!(
+            context.resolve(Focus::It, Some(far_away)).entities,
+            vec![far_away]
+        );
+
+        let target = context.resolve(Focus::Target, None);
+        assert!(context.holds(Quality::Hot, &target));
+        assert!(context.holds(Quality::Far, &target));
+        assert!(!context.holds(Quality::Cold, &target));
+        assert!(context.holds(Quality::Cold, &context.resolve(Focus::AroundMe, None)));
+        assert!(context.holds(Quality::Hurt, &context.resolve(Focus::Me, None)));
+        assert!(!context.holds(Quality::Near, &context.resolve(Focus::It, None)));
+    }
+}
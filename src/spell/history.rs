@@ -0,0 +1,132 @@
+use std::{collections::VecDeque, path::Path, time::Duration};
+
+use bevy::ecs::{
+    entity::Entity,
+    event::{Event, EventReader},
+    system::{ResMut, Resource},
+};
+use serde::Serialize;
+use thiserror::Error;
+
+use super::{parser::SpellAst, SpellEffect};
+
+// Older casts drop off the end of the history
+pub const HISTORY_LENGTH: usize = 200;
+
+// Where the player's cast history is exported to for looking over spell balance
+pub const CAST_HISTORY_EXPORT: &str = "cast_history.json";
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum CastOutcome {
+    Completed,
+    Interrupted,
+    // Never started, with the reason it was turned down
+    Rejected(String),
+}
+
+impl std::fmt::Display for CastOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CastOutcome::Completed => write!(f, "completed"),
+            CastOutcome::Interrupted => write!(f, "interrupted"),
+            CastOutcome::Rejected(reason) => write!(f, "rejected: {}", reason),
+        }
+    }
+}
+
+// Sent once a spell is over, whichever way it ended
+#[derive(Event, Clone, Debug, Serialize)]
+pub struct SpellCast {
+    pub caster: Option<Entity>,
+    pub source: String,
+    // The spell the oracle translated the source into, if it wasn't one already
+    pub translation: Option<String>,
+    pub ast: SpellAst,
+    // Each effect that went off and what it went off on, in order
+    pub targets: Vec<(SpellEffect, Vec<Entity>)>,
+    pub outcome: CastOutcome,
+    // Game time when the spell ended
+    pub at: Duration,
+}
+
+#[derive(Resource, Default, Debug, Serialize)]
+pub struct CastHistory {
+    casts: VecDeque<SpellCast>,
+}
+
+#[derive(Debug, Error)]
+pub enum CastHistoryError {
+    #[error("Could not write cast history: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not serialise cast history: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl CastHistory {
+    pub fn record(&mut self, cast: SpellCast) {
+        if self.casts.len() >= HISTORY_LENGTH {
+            self.casts.pop_front();
+        }
+        self.casts.push_back(cast);
+    }
+
+    // Newest first
+    pub fn recent(&self, count: usize) -> impl Iterator<Item = &SpellCast> {
+        self.casts.iter().rev().take(count)
+    }
+
+    pub fn export(&self, path: impl AsRef<Path>) -> Result<(), CastHistoryError> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+pub fn record_casts(mut casts: EventReader<SpellCast>, mut history: ResMut<CastHistory>) {
+    for cast in casts.read() {
+        history.record(cast.clone());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::spell::parser::parse_spell;
+
+    #[test]
+    fn keeps_the_most_recent_casts() {
+        let ast = parse_spell("heat target").unwrap();
+        let cast = |index: u64| SpellCast {
+            caster: Some(Entity::from_raw(1)),
+            source: "heat target".to_string(),
+            translation: None,
+            ast: ast.clone(),
+            targets: vec![(
+                SpellEffect::Heat { reverse: false },
+                vec![Entity::from_raw(2)],
+            )],
+            outcome: CastOutcome::Completed,
+            at: Duration::from_secs(index),
+        };
+
+        let mut history = CastHistory::default();
+        for index in 0..HISTORY_LENGTH as u64 + 5 {
+            history.record(cast(index));
+        }
+
+        let recent = history
+            .recent(2)
+            .map(|cast| cast.at.as_secs())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            recent,
+            vec![HISTORY_LENGTH as u64 + 4, HISTORY_LENGTH as u64 + 3]
+        );
+        assert_eq!(history.casts.len(), HISTORY_LENGTH);
+
+        let json = serde_json::to_value(&history).unwrap();
+        assert_eq!(json["casts"][0]["source"], "heat target");
+        assert_eq!(json["casts"][0]["outcome"], "Completed");
+        assert_eq!(json["casts"][0]["at"]["secs"], 5);
+    }
+}
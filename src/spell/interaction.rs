@@ -0,0 +1,320 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        system::{Commands, Local, Query, Res, Resource},
+        world::World,
+    },
+    hierarchy::despawn_with_children_recursive,
+    math::Vec2,
+    prelude::{App, Asset, AssetApp, AssetServer, Assets, Handle, Plugin, Startup},
+    reflect::TypePath,
+    time::{Timer, TimerMode},
+    transform::components::Transform,
+    utils::{BoxedFuture, HashSet},
+};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::Game;
+
+use super::{
+    lifetime::Lifetime,
+    shape::{Obstacle, Projectile},
+    temperature::Temperature,
+};
+
+#[derive(Component, Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+pub enum ObjectKind {
+    Rock,
+    Fire,
+    Fireball,
+    Tree,
+    Person,
+    Sword,
+    Arrow,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+pub enum EffectKind {
+    Lift,
+    Heat,
+    Cool,
+    Compress,
+    Scatter,
+    Transform,
+    Push,
+    Dispel,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub enum Outcome {
+    SetTemperature(f32),
+    Despawn,
+    // Seconds until the object is gone
+    DespawnAfter(f32),
+    Become(ObjectKind),
+    // Sends the object flying away from the caster at this speed
+    Launch(f32),
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Rule {
+    pub effect: EffectKind,
+    pub object: ObjectKind,
+    pub outcomes: Vec<Outcome>,
+}
+
+#[derive(TypePath, Asset, Clone, Debug, Deserialize, PartialEq)]
+pub struct InteractionTable {
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Resource)]
+pub struct Interactions(pub Handle<InteractionTable>);
+
+#[derive(Default)]
+pub struct InteractionsPlugin;
+
+impl Plugin for InteractionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<InteractionTable>()
+            .register_asset_loader(InteractionTableLoader)
+            .add_systems(Startup, load_interactions);
+    }
+}
+
+pub struct InteractionTableLoader;
+
+#[derive(Debug, Error)]
+pub enum InteractionTableLoaderError {
+    #[error("Could not load interaction table: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse interaction table: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for InteractionTableLoader {
+    type Asset = InteractionTable;
+    type Settings = ();
+    type Error = InteractionTableLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut bevy::asset::LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+
+            Ok(ron::de::from_bytes(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        static EXTENSIONS: &[&str] = &["interactions.ron"];
+        EXTENSIONS
+    }
+}
+
+fn load_interactions(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(Interactions(asset_server.load("elements.interactions.ron")));
+}
+
+impl InteractionTable {
+    pub fn outcomes(&self, effect: EffectKind, object: ObjectKind) -> Vec<Outcome> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.effect == effect && rule.object == object)
+            .flat_map(|rule| rule.outcomes.iter().copied())
+            .collect()
+    }
+}
+
+impl Outcome {
+    fn apply(&self, world: &mut World, entity: Entity, origin: Option<Vec2>) {
+        match self {
+            Outcome::SetTemperature(degrees) => {
+                if let Some(mut temperature) = world.get_mut::<Temperature>(entity) {
+                    temperature.degrees = *degrees;
+                }
+            }
+            Outcome::Despawn => despawn_with_children_recursive(world, entity),
+            Outcome::DespawnAfter(seconds) => {
+                world
+                    .entity_mut(entity)
+                    .insert(Lifetime(Timer::from_seconds(*seconds, TimerMode::Once)));
+            }
+            Outcome::Become(kind) => {
+                let appearance = world
+                    .get_resource::<Game>()
+                    .and_then(|game| game.entity_factory.as_ref())
+                    .and_then(|entity_factory| entity_factory.appearance(*kind));
+
+                let mut object = world.entity_mut(entity);
+                object.insert(*kind);
+                if let Some(appearance) = appearance {
+                    object.insert(appearance);
+                }
+            }
+            Outcome::Launch(speed) => {
+                let Some(position) = world
+                    .get::<Transform>(entity)
+                    .map(|transform| transform.translation.truncate())
+                else {
+                    return;
+                };
+
+                let heading = origin
+                    .and_then(|origin| (position - origin).try_normalize())
+                    .unwrap_or(Vec2::Y);
+
+                world
+                    .entity_mut(entity)
+                    .remove::<Obstacle>()
+                    .insert(Projectile {
+                        velocity: heading * *speed,
+                        lifetime: Timer::from_seconds(3.0, TimerMode::Once),
+                    });
+            }
+        }
+    }
+}
+
+// What the loaded table says happens, nothing while it's still loading
+pub fn outcomes(world: &World, effect: EffectKind, object: ObjectKind) -> Vec<Outcome> {
+    let (Some(interactions), Some(tables)) = (
+        world.get_resource::<Interactions>(),
+        world.get_resource::<Assets<InteractionTable>>(),
+    ) else {
+        return Vec::new();
+    };
+
+    tables
+        .get(&interactions.0)
+        .map(|table| table.outcomes(effect, object))
+        .unwrap_or_default()
+}
+
+// Runs after the effect itself, so the rules see whatever the effect left behind
+pub fn interact(commands: &mut Commands, effect: EffectKind, entity: Entity, origin: Option<Vec2>) {
+    commands.add(move |world: &mut World| {
+        let Some(object) = world.get::<ObjectKind>(entity).copied() else {
+            return;
+        };
+
+        for outcome in outcomes(world, effect, object) {
+            // An earlier outcome may have already got rid of it
+            if world.get_entity(entity).is_none() {
+                return;
+            }
+
+            outcome.apply(world, entity, origin);
+        }
+    });
+}
+
+// How close fire has to get to something to count as heating it
+const TOUCH_RADIUS: f32 = 32.0;
+
+// Fire heats whatever it touches, so things it drifts or flies into get the same rules as if a
+// heat spell had landed on them. Only once per touch, or the rules would start over every frame.
+pub fn touch_fire(
+    mut commands: Commands,
+    objects: Query<(Entity, &Transform, &ObjectKind)>,
+    mut touching: Local<HashSet<(Entity, Entity)>>,
+) {
+    let fires = objects
+        .iter()
+        .filter(|(_, _, kind)| matches!(kind, ObjectKind::Fire | ObjectKind::Fireball))
+        .map(|(entity, transform, _)| (entity, transform.translation.truncate()));
+
+    let mut touches = HashSet::new();
+    for (fire, origin) in fires {
+        for (object, transform, _) in &objects {
+            let position = transform.translation.truncate();
+            if object == fire || position.distance(origin) > TOUCH_RADIUS {
+                continue;
+            }
+
+            touches.insert((fire, object));
+            if !touching.contains(&(fire, object)) {
+                interact(&mut commands, EffectKind::Heat, object, Some(origin));
+            }
+        }
+    }
+
+    *touching = touches;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bevy::ecs::system::{IntoSystem, System};
+
+    use crate::spell::temperature::IGNITION_POINT;
+
+    #[test]
+    fn reads_the_shipped_table() {
+        let table: InteractionTable =
+            ron::de::from_str(include_str!("../../assets/elements.interactions.ron")).unwrap();
+
+        assert_eq!(
+            table.outcomes(EffectKind::Cool, ObjectKind::Fire),
+            vec![Outcome::Despawn]
+        );
+        assert!(table
+            .outcomes(EffectKind::Compress, ObjectKind::Fire)
+            .contains(&Outcome::Become(ObjectKind::Fireball)));
+        assert!(table
+            .outcomes(EffectKind::Push, ObjectKind::Person)
+            .is_empty());
+
+        // Rocks get hot but don't burn
+        let [Outcome::SetTemperature(rock)] =
+            table.outcomes(EffectKind::Heat, ObjectKind::Rock)[..]
+        else {
+            panic!("Heating a rock should only set its temperature");
+        };
+        assert!(rock < IGNITION_POINT);
+    }
+
+    #[test]
+    fn fire_heats_what_it_touches_once() {
+        let table: InteractionTable =
+            ron::de::from_str(include_str!("../../assets/elements.interactions.ron")).unwrap();
+        let mut tables = Assets::<InteractionTable>::default();
+        let handle = tables.add(table);
+
+        let mut world = World::new();
+        world.insert_resource(tables);
+        world.insert_resource(Interactions(handle));
+
+        let at = |x| Transform::from_xyz(x, 0.0, 10.0);
+        world.spawn((at(0.0), ObjectKind::Fire));
+        let tree = world
+            .spawn((at(20.0), ObjectKind::Tree, Temperature::default()))
+            .id();
+        let far = world
+            .spawn((at(200.0), ObjectKind::Tree, Temperature::default()))
+            .id();
+
+        let mut touch = IntoSystem::into_system(touch_fire);
+        touch.initialize(&mut world);
+        touch.run((), &mut world);
+        touch.apply_deferred(&mut world);
+
+        assert_eq!(world.get::<Temperature>(tree).unwrap().degrees, 600.0);
+        assert!(world.get::<Lifetime>(tree).is_some());
+        assert!(world.get::<Lifetime>(far).is_none());
+
+        // Still touching, so the tree's burning down isn't started over
+        world.entity_mut(tree).remove::<Lifetime>();
+        touch.run((), &mut world);
+        touch.apply_deferred(&mut world);
+        assert!(world.get::<Lifetime>(tree).is_none());
+    }
+}
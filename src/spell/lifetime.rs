@@ -0,0 +1,37 @@
+use std::time::Duration;
+
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        system::{Commands, Query, Res},
+    },
+    hierarchy::DespawnRecursiveExt,
+    time::{Time, Timer, TimerMode},
+};
+
+// How long the things spells leave lying around stick about for
+pub const FIRE_LIFETIME: Duration = Duration::from_secs(5);
+pub const RUBBLE_LIFETIME: Duration = Duration::from_secs(10);
+
+#[derive(Component)]
+pub struct Lifetime(pub Timer);
+
+impl Lifetime {
+    pub fn new(duration: Duration) -> Self {
+        Lifetime(Timer::new(duration, TimerMode::Once))
+    }
+}
+
+pub fn expire_lifetimes(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Lifetime)>,
+) {
+    for (entity, mut lifetime) in &mut query {
+        lifetime.0.tick(time.delta());
+        if lifetime.0.finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
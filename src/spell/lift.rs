@@ -0,0 +1,220 @@
+use std::time::Duration;
+
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::{Event, EventReader, EventWriter},
+        query::{With, Without},
+        system::{Commands, Query, Res},
+        world::World,
+    },
+    hierarchy::{BuildWorldChildren, DespawnRecursiveExt},
+    math::{Vec2, Vec3},
+    prelude::default,
+    render::color::Color,
+    sprite::{Sprite, SpriteBundle},
+    time::{Time, Timer, TimerMode},
+    transform::components::Transform,
+};
+
+use crate::{
+    agent::{Damaged, Health},
+    terrain::{alter_terrain, TerrainAlteration},
+};
+
+use super::compress::Mass;
+
+// How long a lifted entity hangs in the air before gravity takes over
+pub const LIFT_DURATION: Duration = Duration::from_secs(2);
+
+const LIFT_HEIGHT: f32 = 96.0;
+const RISE_SPEED: f32 = 192.0;
+const GRAVITY: f32 = 980.0;
+
+// Anything within this distance of a landing gets hit by it
+const IMPACT_RADIUS: f32 = 48.0;
+const DAMAGE_PER_SPEED: f32 = 0.05;
+// Only a fall from a full lift hits hard enough to crater the ground
+const CRATER_SPEED: f32 = 400.0;
+
+#[derive(Component)]
+pub struct Shadow;
+
+#[derive(Component, Debug)]
+pub struct Lifted {
+    // Height above the ground, drawn by shifting the sprite up the screen
+    pub height: f32,
+    pub vertical_speed: f32,
+    pub hold: Timer,
+    shadow: Entity,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct Landed {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub speed: f32,
+}
+
+impl Lifted {
+    fn new(shadow: Entity) -> Self {
+        Lifted {
+            height: 0.0,
+            vertical_speed: 0.0,
+            hold: Timer::new(LIFT_DURATION, TimerMode::Once),
+            shadow,
+        }
+    }
+
+    // Returns the speed it hit the ground at once it lands
+    pub fn step(&mut self, delta: Duration) -> Option<f32> {
+        self.hold.tick(delta);
+        let delta = delta.as_secs_f32();
+
+        if !self.hold.finished() {
+            self.vertical_speed = 0.0;
+            self.height = (self.height + RISE_SPEED * delta).min(LIFT_HEIGHT);
+            return None;
+        }
+
+        self.vertical_speed -= GRAVITY * delta;
+        self.height += self.vertical_speed * delta;
+
+        if self.height > 0.0 {
+            return None;
+        }
+
+        self.height = 0.0;
+        Some(-self.vertical_speed)
+    }
+}
+
+fn new_shadow_bundle() -> (SpriteBundle, Shadow) {
+    (
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(0.0, 0.0, 0.0, 0.4),
+                custom_size: Some(Vec2::new(16.0, 6.0)),
+                ..default()
+            },
+            transform: Transform::from_translation(Vec3::new(0.0, 0.0, -0.1)),
+            ..default()
+        },
+        Shadow,
+    )
+}
+
+// Lifting something already in the air keeps it up there for longer
+pub fn lift(commands: &mut Commands, entity: Entity) {
+    commands.add(move |world: &mut World| {
+        let Some(mut target) = world.get_entity_mut(entity) else {
+            return;
+        };
+
+        if let Some(mut lifted) = target.get_mut::<Lifted>() {
+            lifted.hold.reset();
+            return;
+        }
+
+        let shadow = world.spawn(new_shadow_bundle()).id();
+        world
+            .entity_mut(entity)
+            .add_child(shadow)
+            .insert(Lifted::new(shadow));
+    });
+}
+
+pub fn fall_lifted(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Lifted, &mut Transform)>,
+    mut shadows: Query<&mut Transform, (With<Shadow>, Without<Lifted>)>,
+    mut landings: EventWriter<Landed>,
+) {
+    for (entity, mut lifted, mut transform) in &mut query {
+        let previous = lifted.height;
+        let landed = lifted.step(time.delta());
+
+        transform.translation.y += lifted.height - previous;
+
+        if let Ok(mut shadow) = shadows.get_mut(lifted.shadow) {
+            shadow.translation.y = -lifted.height / transform.scale.y;
+        }
+
+        let Some(speed) = landed else {
+            continue;
+        };
+
+        commands.entity(lifted.shadow).despawn_recursive();
+        commands.entity(entity).remove::<Lifted>();
+
+        landings.send(Landed {
+            entity,
+            position: transform.translation.truncate(),
+            speed,
+        });
+    }
+}
+
+// Both whatever fell and whatever it fell on take the hit, heavier things hit harder
+pub fn apply_impact_damage(
+    mut landings: EventReader<Landed>,
+    masses: Query<&Mass>,
+    mut query: Query<(Entity, &Transform, &mut Health)>,
+    mut damaged: EventWriter<Damaged>,
+) {
+    for landing in landings.read() {
+        let mass = masses.get(landing.entity).map_or(1.0, |mass| mass.0);
+        let damage = landing.speed * DAMAGE_PER_SPEED * mass;
+
+        for (entity, transform, mut health) in &mut query {
+            let hit = entity == landing.entity
+                || transform.translation.truncate().distance(landing.position) <= IMPACT_RADIUS;
+
+            if hit {
+                health.damage(damage);
+                damaged.send(Damaged {
+                    entity,
+                    amount: damage,
+                });
+            }
+        }
+    }
+}
+
+pub fn crater_ground(mut commands: Commands, mut landings: EventReader<Landed>) {
+    for landing in landings.read() {
+        if landing.speed >= CRATER_SPEED {
+            alter_terrain(&mut commands, landing.position, TerrainAlteration::Cratered);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rises_holds_then_falls() {
+        let mut lifted = Lifted::new(Entity::from_raw(0));
+        let frame = Duration::from_millis(100);
+
+        assert_eq!(lifted.step(frame), None);
+        assert!(lifted.height > 0.0);
+
+        while lifted.hold.remaining() > frame {
+            assert_eq!(lifted.step(frame), None);
+        }
+        assert_eq!(lifted.height, LIFT_HEIGHT);
+
+        let speed = std::iter::repeat_with(|| lifted.step(frame))
+            .take(100)
+            .flatten()
+            .next()
+            .expect("It should land eventually");
+
+        assert_eq!(lifted.height, 0.0);
+        assert!(speed > 0.0);
+    }
+}
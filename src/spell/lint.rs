@@ -0,0 +1,138 @@
+use std::mem::{discriminant, Discriminant};
+
+use super::{
+    is_reversed,
+    parser::{Chain, Effect, Focus, SpellAst},
+};
+
+// Spells that parse fine but probably don't do what the caster meant. Effects are counted from 1,
+// the way a person reading the spell would.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Lint {
+    // Nothing has been spawned yet for "it" to refer to
+    ItBeforeAnything { effect: usize },
+    // An even number of reverses undo each other but are still paid for
+    CancellingModifiers { effect: usize },
+    // Two effects started together on the same focus that undo each other
+    Contradictory { first: usize, second: usize },
+}
+
+impl std::fmt::Display for Lint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Lint::ItBeforeAnything { effect } => write!(
+                f,
+                "effect {} targets 'it' but nothing before it leaves anything behind",
+                effect
+            ),
+            Lint::CancellingModifiers { effect } => write!(
+                f,
+                "the modifiers on effect {} cancel each other out but still cost mana",
+                effect
+            ),
+            Lint::Contradictory { first, second } => write!(
+                f,
+                "effects {} and {} start together on the same focus and undo each other",
+                first, second
+            ),
+        }
+    }
+}
+
+// Heat is the only effect that leaves something behind, cooling doesn't
+fn spawns(effect: &Effect) -> bool {
+    matches!(effect, Effect::Heat(_, modifiers) if !is_reversed(modifiers))
+}
+
+// The effect's focus and which way it pushes, for effects that have an opposite
+fn polarity(effect: &Effect) -> Option<(Discriminant<Effect>, Focus, bool)> {
+    match effect {
+        Effect::Heat(focus, modifiers) | Effect::Compress(focus, modifiers) => {
+            Some((discriminant(effect), *focus, is_reversed(modifiers)))
+        }
+        _ => None,
+    }
+}
+
+fn focus(effect: &Effect) -> Focus {
+    match effect {
+        Effect::Lift(focus)
+        | Effect::Compress(focus, _)
+        | Effect::Transform(focus, _)
+        | Effect::Heat(focus, _)
+        | Effect::Push(focus, _)
+        | Effect::Dispel(focus) => *focus,
+    }
+}
+
+pub fn lint(ast: &SpellAst) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    let mut spawned = false;
+    let mut group_start = 0;
+
+    for (index, chained) in ast.effects.iter().enumerate() {
+        let effect = &chained.effect;
+
+        if focus(effect) == Focus::It && !spawned {
+            lints.push(Lint::ItBeforeAnything { effect: index + 1 });
+        }
+        spawned |= spawns(effect);
+
+        if let Effect::Heat(_, modifiers) | Effect::Compress(_, modifiers) = effect {
+            if !modifiers.is_empty() && !is_reversed(modifiers) {
+                lints.push(Lint::CancellingModifiers { effect: index + 1 });
+            }
+        }
+
+        if chained.chain == Chain::Then {
+            group_start = index;
+        }
+
+        let Some((kind, focus, reverse)) = polarity(effect) else {
+            continue;
+        };
+
+        let opposite = ast.effects[group_start..index]
+            .iter()
+            .position(|other| polarity(&other.effect) == Some((kind, focus, !reverse)));
+
+        if let Some(other) = opposite {
+            lints.push(Lint::Contradictory {
+                first: group_start + other + 1,
+                second: index + 1,
+            });
+        }
+    }
+
+    lints
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::spell::parser::parse_spell;
+
+    #[test]
+    fn warns_about_spells_that_undo_themselves() {
+        let lints = |spell: &str| lint(&parse_spell(spell).unwrap());
+
+        assert!(lints("heat target then push it back").is_empty());
+        assert_eq!(
+            lints("lift it and heat me"),
+            vec![Lint::ItBeforeAnything { effect: 1 }]
+        );
+        assert_eq!(
+            lints("heat target reverse reverse"),
+            vec![Lint::CancellingModifiers { effect: 1 }]
+        );
+        assert_eq!(
+            lints("heat me and lift me and heat me reverse"),
+            vec![Lint::Contradictory {
+                first: 1,
+                second: 3
+            }]
+        );
+        // Cooling down after heating up is fine once the heat is done
+        assert!(lints("heat me then heat me reverse").is_empty());
+    }
+}
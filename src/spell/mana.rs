@@ -0,0 +1,118 @@
+use bevy::{
+    ecs::{component::Component, system::Query, system::Res},
+    time::Time,
+};
+
+use super::parser::{ChainedEffect, Effect, Focus, Repeat, SpellAst};
+
+pub const MAX_MANA: f32 = 100.0;
+pub const MANA_REGEN: f32 = 5.0;
+
+// Every effect after the first costs extra to hold together
+const CHAIN_COST: f32 = 5.0;
+const MODIFIER_COST: f32 = 5.0;
+const AREA_MULTIPLIER: f32 = 2.0;
+const GUARD_COST: f32 = 5.0;
+
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Mana {
+    pub current: f32,
+    pub max: f32,
+    // Mana regained per second
+    pub regen: f32,
+}
+
+impl Mana {
+    pub fn new(max: f32, regen: f32) -> Self {
+        Mana {
+            current: max,
+            max,
+            regen,
+        }
+    }
+
+    pub fn try_spend(&mut self, cost: f32) -> bool {
+        if self.current < cost {
+            return false;
+        }
+
+        self.current -= cost;
+        true
+    }
+}
+
+impl Default for Mana {
+    fn default() -> Self {
+        Mana::new(MAX_MANA, MANA_REGEN)
+    }
+}
+
+fn effect_cost(effect: &Effect) -> f32 {
+    let (base, focus, modifiers) = match effect {
+        Effect::Lift(focus) => (10.0, focus, 0),
+        Effect::Push(focus, _) => (8.0, focus, 0),
+        Effect::Heat(focus, modifiers) => (10.0, focus, modifiers.len()),
+        Effect::Compress(focus, modifiers) => (15.0, focus, modifiers.len()),
+        Effect::Transform(focus, _) => (25.0, focus, 0),
+        Effect::Dispel(focus) => (15.0, focus, 0),
+    };
+
+    let area = match focus {
+        Focus::AroundMe | Focus::AroundTarget => AREA_MULTIPLIER,
+        Focus::Target | Focus::Me | Focus::It => 1.0,
+    };
+
+    base * area + modifiers as f32 * MODIFIER_COST
+}
+
+// Repeats are paid for up front, a timed repeat goes off once a second so each second costs the
+// same as one more cast
+fn clause_cost(chained: &ChainedEffect) -> f32 {
+    let repeats = match chained.repeat {
+        None => 1,
+        Some(Repeat::Times(count)) | Some(Repeat::Seconds(count)) => count,
+    };
+    let guard = if chained.guard.is_some() {
+        GUARD_COST
+    } else {
+        0.0
+    };
+
+    effect_cost(&chained.effect) * repeats as f32 + guard
+}
+
+pub fn spell_cost(ast: &SpellAst) -> f32 {
+    let effects = ast.effects.iter().map(clause_cost).sum::<f32>();
+
+    effects + ast.effects.len().saturating_sub(1) as f32 * CHAIN_COST
+}
+
+pub fn regenerate_mana(time: Res<Time>, mut query: Query<&mut Mana>) {
+    for mut mana in &mut query {
+        mana.current = (mana.current + mana.regen * time.delta_seconds()).min(mana.max);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::spell::parser::parse_spell;
+
+    fn cost(spell: &str) -> f32 {
+        spell_cost(&parse_spell(spell).unwrap())
+    }
+
+    #[test]
+    fn costs_grow_with_area_chains_and_modifiers() {
+        assert_eq!(cost("heat target"), 10.0);
+        assert_eq!(cost("heat around_target"), 20.0);
+        assert_eq!(cost("heat target reverse"), 15.0);
+        assert_eq!(cost("heat target then lift it"), 25.0);
+        assert!(cost("transform target into tree") > cost("push target back"));
+        assert_eq!(cost("lift target thrice"), 30.0);
+        assert_eq!(
+            cost("if target is hot then heat target for 2 seconds"),
+            25.0
+        );
+    }
+}
@@ -0,0 +1,759 @@
+use bevy::{
+    asset::Handle,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::{Event, EventReader, EventWriter},
+        query::{Or, With, Without},
+        system::{Commands, Query, Res},
+    },
+    math::{Vec2, Vec3},
+    prelude::default,
+    sprite::{SpriteSheetBundle, TextureAtlas, TextureAtlasSprite},
+    time::{Time, Timer, TimerMode},
+    transform::components::Transform,
+};
+
+use serde::{Deserialize, Serialize};
+use std::{path::Path, str::FromStr, time::Duration};
+use thiserror::Error;
+
+use crate::{
+    agent::{Action, AnimationSet, CharacterState, Health},
+    terrain::{alter_terrain, TerrainAlteration},
+    AnimationTimer, EntityFactory, Game,
+};
+
+use self::bytecode::{compile, Vm};
+use self::casting::{Cooldowns, CAST_TIME_PER_MANA, COOLDOWN_PER_MANA};
+use self::compress::{compress_around, push_or_throw, scatter, Mass};
+use self::dispel::{dispel, is_counter_spell, COUNTER_CAST_TIME};
+use self::focus::{FocusContext, SelectedTarget, SpellTarget};
+use self::history::{CastOutcome, SpellCast};
+use self::interaction::{interact, EffectKind, ObjectKind};
+use self::lifetime::{Lifetime, FIRE_LIFETIME, RUBBLE_LIFETIME};
+use self::lift::{lift, Lifted};
+use self::mana::{spell_cost, Mana};
+use self::parser::{parse_spell, Dialect, Direction, Modifier, Shape, SpellAst, SpellParseError};
+use self::push::Knockback;
+use self::shape::{place, transform_into, Projectile};
+use self::status::{afflict, Burning, Compressed, Frozen, Levitating, StatusOverlay};
+use self::temperature::{
+    change_temperature, new_thermal_bundle, Temperature, ThermalBundle, HEAT_PER_CAST,
+};
+
+pub mod bytecode;
+pub mod casting;
+pub mod compress;
+pub mod dispel;
+pub mod focus;
+pub mod history;
+pub mod interaction;
+pub mod lifetime;
+pub mod lift;
+pub mod lint;
+pub mod mana;
+pub mod parser;
+pub mod push;
+pub mod shape;
+pub mod spellbook;
+pub mod status;
+pub mod targeting;
+pub mod temperature;
+pub mod translate;
+
+#[derive(Event, Clone, Debug)]
+pub struct SpellRejected {
+    pub caster: Option<Entity>,
+    pub rejection: Rejection,
+}
+
+#[derive(Clone, Debug)]
+pub enum Rejection {
+    Parse(SpellParseError),
+    // The oracle couldn't make a spell of it either, the error is for what the caster said
+    Untranslated(SpellParseError),
+    Unaffordable { cost: f32, available: f32 },
+    CoolingDown { remaining: Duration },
+}
+
+// The most advanced dialect a caster can cast in
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fluency(pub Dialect);
+
+// Completed casts it takes to become fluent in the next dialect up
+pub const CASTS_TO_ADVANCE: u32 = 20;
+
+// Where how far the player has got is kept between games, next to their spellbook
+pub const PLAYER_FLUENCY: &str = "fluency.ron";
+
+// Completed casts since the caster last moved up a dialect
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Practice(pub u32);
+
+#[derive(Debug, Error)]
+pub enum FluencyError {
+    #[error("Could not access fluency: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not read fluency: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("Could not write fluency: {0}")]
+    Write(#[from] ron::Error),
+}
+
+pub fn load_fluency(path: impl AsRef<Path>) -> Result<(Fluency, Practice), FluencyError> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(ron::de::from_str(&text)?)
+}
+
+pub fn save_fluency(
+    path: impl AsRef<Path>,
+    fluency: Fluency,
+    practice: Practice,
+) -> Result<(), FluencyError> {
+    let text = ron::ser::to_string(&(fluency, practice))?;
+    std::fs::write(path, text)?;
+    Ok(())
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct FluencyGained {
+    pub caster: Entity,
+    pub dialect: Dialect,
+}
+
+impl Practice {
+    // The dialect the caster has just become fluent in, if this cast was the one that did it
+    fn practise(&mut self, fluency: &mut Fluency) -> Option<Dialect> {
+        let next = fluency.0.next()?;
+
+        self.0 += 1;
+        if self.0 < CASTS_TO_ADVANCE {
+            return None;
+        }
+
+        self.0 = 0;
+        fluency.0 = next;
+        Some(next)
+    }
+}
+
+// Casters who practise get better, only spells that went off count
+pub fn practise(
+    mut casts: EventReader<SpellCast>,
+    mut casters: Query<(&mut Fluency, &mut Practice)>,
+    mut gained: EventWriter<FluencyGained>,
+) {
+    for cast in casts.read() {
+        let Some(caster) = cast
+            .caster
+            .filter(|_| cast.outcome == CastOutcome::Completed)
+        else {
+            continue;
+        };
+        let Ok((mut fluency, mut practice)) = casters.get_mut(caster) else {
+            continue;
+        };
+
+        if let Some(dialect) = practice.practise(&mut fluency) {
+            gained.send(FluencyGained { caster, dialect });
+        }
+    }
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SpellFinished {
+    pub spell: Entity,
+    pub caster: Option<Entity>,
+    pub interrupted: bool,
+}
+
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rejection::Parse(error) => write!(f, "{}", error),
+            Rejection::Untranslated(error) => {
+                write!(
+                    f,
+                    "The oracle couldn't make a spell of that either. {}",
+                    error
+                )
+            }
+            Rejection::Unaffordable { cost, available } => write!(
+                f,
+                "Not enough mana, the spell costs {:.0} but only {:.0} is left",
+                cost, available
+            ),
+            Rejection::CoolingDown { remaining } => write!(
+                f,
+                "That spell is still cooling down, ready in {:.1}s",
+                remaining.as_secs_f32()
+            ),
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlobState {
+    Floating,
+    Compressed,
+}
+
+pub type MatterBlobBundleBundle = (
+    SpriteSheetBundle,
+    AnimationSet,
+    AnimationTimer,
+    BlobState,
+    ThermalBundle,
+    Mass,
+    ObjectKind,
+);
+
+pub fn new_matter_blob_bundle(
+    atlas_handle: Handle<TextureAtlas>,
+    animation_set: AnimationSet,
+    degrees: f32,
+    kind: ObjectKind,
+) -> MatterBlobBundleBundle {
+    (
+        SpriteSheetBundle {
+            texture_atlas: atlas_handle.clone(),
+            sprite: TextureAtlasSprite::new(0),
+            transform: Transform::from_scale(Vec3::splat(2.0))
+                .with_translation(Vec3::new(400.0, 10.0, 10.00)),
+            ..default()
+        },
+        animation_set,
+        AnimationTimer(Timer::from_seconds(0.2, TimerMode::Repeating)),
+        BlobState::Floating,
+        new_thermal_bundle(degrees),
+        Mass::default(),
+        kind,
+    )
+}
+
+pub fn animate_blob(
+    time: Res<Time>,
+    game: Res<Game>,
+    mut commands: Commands,
+    mut query: Query<(
+        Entity,
+        &BlobState,
+        &AnimationSet,
+        &mut AnimationTimer,
+        &mut TextureAtlasSprite,
+    )>,
+) {
+    for (entity, anim_set, action_state, mut timer, mut sprite) in &mut query {
+        timer.tick(time.delta());
+        if timer.just_finished() {
+            let (first, last) = (0, 2);
+
+            sprite.index = if sprite.index >= last || sprite.index < first {
+                first
+            } else {
+                sprite.index + 1
+            };
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SpellEffect {
+    Lift,
+    Heat { reverse: bool },
+    Compress { reverse: bool },
+    Transform(Shape),
+    Push(Direction),
+    Dispel,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SpellState {
+    Idle,
+    Casting,
+    Active,
+    Finished,
+    Interrupted,
+    SpawnedEffect(Entity),
+}
+
+impl SpellEffect {
+    // How long anything the effect leaves behind lasts, effects that only change what's already
+    // there leave nothing to clean up
+    fn leftover_lifetime(&self) -> Option<Duration> {
+        match self {
+            SpellEffect::Heat { reverse: false } => Some(FIRE_LIFETIME),
+            SpellEffect::Compress { reverse: true } => Some(RUBBLE_LIFETIME),
+            _ => None,
+        }
+    }
+
+    fn kind(&self) -> EffectKind {
+        match self {
+            SpellEffect::Lift => EffectKind::Lift,
+            SpellEffect::Heat { reverse: false } => EffectKind::Heat,
+            SpellEffect::Heat { reverse: true } => EffectKind::Cool,
+            SpellEffect::Compress { reverse: false } => EffectKind::Compress,
+            SpellEffect::Compress { reverse: true } => EffectKind::Scatter,
+            SpellEffect::Transform(_) => EffectKind::Transform,
+            SpellEffect::Push(_) => EffectKind::Push,
+            SpellEffect::Dispel => EffectKind::Dispel,
+        }
+    }
+
+    // Applies the effect and then whatever the interaction table says happens when it lands on
+    // each of its targets. The power is the mana spent on the whole spell.
+    fn try_apply(
+        &self,
+        commands: &mut Commands,
+        entity_factory: &EntityFactory,
+        target: &SpellTarget,
+        focus_context: &FocusContext,
+        power: f32,
+    ) -> Option<SpellState> {
+        let applied = self.apply(commands, entity_factory, target, focus_context, power);
+
+        let origin = focus_context
+            .caster
+            .and_then(|caster| focus_context.position(caster));
+
+        for entity in &target.entities {
+            interact(commands, self.kind(), *entity, origin);
+        }
+
+        applied
+    }
+
+    fn apply(
+        &self,
+        commands: &mut Commands,
+        entity_factory: &EntityFactory,
+        target: &SpellTarget,
+        focus_context: &FocusContext,
+        power: f32,
+    ) -> Option<SpellState> {
+        let lifetime = self.leftover_lifetime().unwrap_or_default();
+
+        match self {
+            SpellEffect::Lift => {
+                for entity in &target.entities {
+                    lift(commands, *entity);
+                    afflict::<Levitating>(commands, entity_factory, *entity, power);
+                }
+
+                Some(SpellState::Active)
+            }
+            SpellEffect::Heat { reverse } => {
+                let change = if *reverse {
+                    -HEAT_PER_CAST
+                } else {
+                    HEAT_PER_CAST
+                };
+
+                let mut spawned = None;
+                for entity in &target.entities {
+                    change_temperature(commands, *entity, change);
+
+                    let position = focus_context.position(*entity);
+
+                    // Only heating leaves a fire behind, cooling just draws the heat out
+                    if *reverse {
+                        afflict::<Frozen>(commands, entity_factory, *entity, power);
+                        if let Some(position) = position {
+                            alter_terrain(commands, position, TerrainAlteration::Frozen);
+                        }
+                        continue;
+                    }
+                    afflict::<Burning>(commands, entity_factory, *entity, power);
+
+                    let Some(position) = position else {
+                        continue;
+                    };
+                    alter_terrain(commands, position, TerrainAlteration::Scorched);
+
+                    let mut blob = entity_factory.make_fire();
+                    place(&mut blob.0.transform, position);
+                    spawned = Some(commands.spawn((blob, Lifetime::new(lifetime))).id());
+                }
+
+                match spawned {
+                    Some(entity_id) => Some(SpellState::SpawnedEffect(entity_id)),
+                    None => Some(SpellState::Active),
+                }
+            }
+            SpellEffect::Transform(shape) => {
+                let origin = focus_context
+                    .caster
+                    .and_then(|caster| focus_context.position(caster));
+
+                for entity in &target.entities {
+                    let Some(position) = focus_context.position(*entity) else {
+                        continue;
+                    };
+                    let heading = origin.map_or(Vec2::Y, |origin| position - origin);
+
+                    transform_into(commands, *entity, *shape, position, heading);
+                }
+
+                Some(SpellState::Finished)
+            }
+            SpellEffect::Push(direction) => {
+                let origin = focus_context
+                    .caster
+                    .and_then(|caster| focus_context.position(caster));
+
+                for entity in &target.entities {
+                    let Some(position) = focus_context.position(*entity) else {
+                        continue;
+                    };
+                    let Some(heading) = direction.heading(position, origin) else {
+                        continue;
+                    };
+
+                    push_or_throw(commands, *entity, *direction, heading);
+                }
+
+                Some(SpellState::Active)
+            }
+            SpellEffect::Compress { reverse } => {
+                for entity in &target.entities {
+                    if *reverse {
+                        scatter(commands, *entity, lifetime);
+                        continue;
+                    }
+
+                    let Some(position) = focus_context.position(*entity) else {
+                        continue;
+                    };
+
+                    compress_around(commands, position);
+                    afflict::<Compressed>(commands, entity_factory, *entity, power);
+                }
+
+                Some(SpellState::Finished)
+            }
+            SpellEffect::Dispel => {
+                for entity in &target.entities {
+                    dispel(commands, *entity, power, focus_context.caster);
+                }
+
+                Some(SpellState::Finished)
+            }
+        }
+    }
+}
+
+// Reversing twice undoes the reversal
+fn is_reversed(modifiers: &[Modifier]) -> bool {
+    let reversals = modifiers
+        .iter()
+        .filter(|modifier| **modifier == Modifier::Reverse)
+        .count();
+
+    reversals % 2 == 1
+}
+
+#[derive(Component)]
+pub struct Spell {
+    caster: Option<Entity>,
+    // What the caster actually said, for the cast history
+    source: String,
+    // What the oracle made of it, when it had to be translated
+    translation: Option<String>,
+    ast: SpellAst,
+    vm: Vm,
+    // Each effect that went off so far and what it went off on
+    targets: Vec<(SpellEffect, Vec<Entity>)>,
+    state: SpellState,
+    cast_time: Timer,
+    cooldown: Duration,
+    cost: f32,
+    paid: bool,
+    interrupted: bool,
+}
+
+impl Spell {
+    pub fn cast_by(mut self, caster: Entity) -> Self {
+        self.caster = Some(caster);
+        self
+    }
+
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.source = source.to_string();
+        self
+    }
+
+    pub fn with_translation(mut self, translation: &str) -> Self {
+        self.translation = Some(translation.to_string());
+        self
+    }
+
+    fn cast(&self, outcome: CastOutcome, at: Duration) -> SpellCast {
+        SpellCast {
+            caster: self.caster,
+            source: self.source.clone(),
+            translation: self.translation.clone(),
+            ast: self.ast.clone(),
+            targets: self.targets.clone(),
+            outcome,
+            at,
+        }
+    }
+
+    // Stops the spell before any more of its effects start, whatever already happened stays
+    pub fn interrupt(&mut self) {
+        self.interrupted = true;
+    }
+
+    // Spells are paid for up front, before any of their effects start. Casters without a mana
+    // pool cast for free.
+    fn pay(
+        &mut self,
+        mana: Option<&mut Mana>,
+        cooldowns: Option<&Cooldowns>,
+    ) -> Result<(), Rejection> {
+        if self.paid {
+            return Ok(());
+        }
+
+        if let Some(remaining) = cooldowns.and_then(|cooldowns| cooldowns.remaining(&self.ast)) {
+            return Err(Rejection::CoolingDown { remaining });
+        }
+
+        if let Some(mana) = mana {
+            if !mana.try_spend(self.cost) {
+                return Err(Rejection::Unaffordable {
+                    cost: self.cost,
+                    available: mana.current,
+                });
+            }
+        }
+
+        self.paid = true;
+        Ok(())
+    }
+
+    // Casting -> Active -> Finished, or Interrupted if the caster gets hurt along the way
+    fn update(
+        &mut self,
+        commands: &mut Commands,
+        entity_factory: &EntityFactory,
+        focus_context: &FocusContext,
+        delta: Duration,
+    ) -> SpellState {
+        let running = matches!(self.state, SpellState::Casting | SpellState::Active);
+        if self.interrupted && running {
+            self.state = SpellState::Interrupted;
+        }
+
+        match self.state {
+            SpellState::Idle => self.state = SpellState::Casting,
+            SpellState::Casting => {
+                self.cast_time.tick(delta);
+                if self.cast_time.finished() {
+                    self.state = SpellState::Active;
+                }
+            }
+            SpellState::Active => {
+                let power = self.cost;
+                self.vm.advance(
+                    delta,
+                    |focus, it| focus_context.resolve(focus, it),
+                    |effect, target| {
+                        self.targets.push((*effect, target.entities.clone()));
+                        effect.try_apply(commands, entity_factory, target, focus_context, power)
+                    },
+                    |quality, target| focus_context.holds(quality, target),
+                    |_, target| focus_context.settled(target),
+                );
+                if self.vm.finished() {
+                    self.state = SpellState::Finished;
+                }
+            }
+            _ => {}
+        }
+
+        self.state.clone()
+    }
+}
+
+impl From<SpellAst> for Spell {
+    fn from(ast: SpellAst) -> Self {
+        let cost = spell_cost(&ast);
+        let cast_time = if is_counter_spell(&ast) {
+            CAST_TIME_PER_MANA.mul_f32(cost * COUNTER_CAST_TIME)
+        } else {
+            CAST_TIME_PER_MANA.mul_f32(cost)
+        };
+
+        Spell {
+            caster: None,
+            source: String::new(),
+            translation: None,
+            vm: Vm::new(compile(&ast)),
+            ast,
+            targets: Vec::new(),
+            state: SpellState::Idle,
+            cast_time: Timer::new(cast_time, TimerMode::Once),
+            cooldown: COOLDOWN_PER_MANA.mul_f32(cost),
+            cost,
+            paid: false,
+            interrupted: false,
+        }
+    }
+}
+
+impl FromStr for Spell {
+    type Err = SpellParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        parse_spell(input).map(|ast| Spell::from(ast).with_source(input))
+    }
+}
+
+// Anything with a sprite can be the focus of a spell, apart from the overlays showing statuses
+type Targetable = (With<TextureAtlasSprite>, Without<StatusOverlay>);
+// Lifts and pushes are over once these are gone
+type InMotion = Or<(With<Lifted>, With<Knockback>, With<Projectile>)>;
+// What guards can ask about a target
+type TargetState<'a> = (
+    Entity,
+    &'a Transform,
+    Option<&'a Temperature>,
+    Option<&'a Health>,
+);
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_spell(
+    time: Res<Time>,
+    game: Res<Game>,
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Spell)>,
+    targetable: Query<TargetState, Targetable>,
+    selections: Query<&SelectedTarget>,
+    in_motion: Query<Entity, InMotion>,
+    mut casters: Query<(
+        Option<&mut Mana>,
+        Option<&mut Cooldowns>,
+        Option<&mut CharacterState>,
+    )>,
+    mut rejections: EventWriter<SpellRejected>,
+    mut finished: EventWriter<SpellFinished>,
+    mut casts: EventWriter<SpellCast>,
+) {
+    let Some(entity_factory) = game.entity_factory.as_ref() else {
+        return;
+    };
+
+    let mut moving = in_motion.iter().collect::<Vec<_>>();
+    moving.sort();
+
+    let mut positions = Vec::new();
+    let mut temperatures = Vec::new();
+    let mut hurt = Vec::new();
+    for (entity, transform, temperature, health) in &targetable {
+        positions.push((entity, transform.translation.truncate()));
+        if let Some(temperature) = temperature {
+            temperatures.push((entity, temperature.degrees));
+        }
+        if health.is_some_and(|health| health.current < health.max) {
+            hurt.push(entity);
+        }
+    }
+    // Query order isn't something a replay can count on
+    positions.sort_by_key(|(entity, _)| *entity);
+    temperatures.sort_by_key(|(entity, _)| *entity);
+    hurt.sort();
+
+    for (entity, mut spell) in &mut query {
+        let (mut mana, mut cooldowns, mut character) = spell
+            .caster
+            .and_then(|caster| casters.get_mut(caster).ok())
+            .unwrap_or((None, None, None));
+
+        if let Err(rejection) = spell.pay(mana.as_deref_mut(), cooldowns.as_deref()) {
+            let outcome = CastOutcome::Rejected(rejection.to_string());
+            casts.send(spell.cast(outcome, time.elapsed()));
+            rejections.send(SpellRejected {
+                caster: spell.caster,
+                rejection,
+            });
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let focus_context = FocusContext {
+            caster: spell.caster,
+            selected: spell
+                .caster
+                .and_then(|caster| selections.get(caster).ok())
+                .map(|selection| selection.0),
+            positions: &positions,
+            temperatures: &temperatures,
+            hurt: &hurt,
+            moving: &moving,
+        };
+
+        let previous = spell.state.clone();
+        let state = spell.update(&mut commands, entity_factory, &focus_context, time.delta());
+
+        // The caster is busy with the incantation until the cast time is up
+        if let Some(character) = character.as_deref_mut() {
+            if state == SpellState::Casting {
+                character.action = Action::Attacking;
+            } else if previous == SpellState::Casting {
+                character.action = Action::Idle;
+            }
+        }
+
+        if state == SpellState::Finished {
+            if let Some(cooldowns) = cooldowns.as_deref_mut() {
+                cooldowns.start(spell.ast.clone(), spell.cooldown);
+            }
+        }
+
+        if matches!(state, SpellState::Finished | SpellState::Interrupted) {
+            finished.send(SpellFinished {
+                spell: entity,
+                caster: spell.caster,
+                interrupted: state == SpellState::Interrupted,
+            });
+
+            let outcome = if state == SpellState::Interrupted {
+                CastOutcome::Interrupted
+            } else {
+                CastOutcome::Completed
+            };
+            casts.send(spell.cast(outcome, time.elapsed()));
+
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn practice_unlocks_each_dialect_in_turn() {
+        let mut fluency = Fluency(Dialect::Apprentice);
+        let mut practice = Practice::default();
+
+        let gained = (0..CASTS_TO_ADVANCE * 3)
+            .filter_map(|_| practice.practise(&mut fluency))
+            .collect::<Vec<_>>();
+
+        assert_eq!(gained, vec![Dialect::Adept, Dialect::Archmage]);
+        assert_eq!(fluency.0, Dialect::LATEST);
+        assert_eq!(practice.0, 0);
+
+        let path = std::env::temp_dir().join("spellfire-fluency-test.ron");
+        save_fluency(&path, Fluency(Dialect::Adept), Practice(7)).unwrap();
+        assert_eq!(
+            load_fluency(&path).unwrap(),
+            (Fluency(Dialect::Adept), Practice(7))
+        );
+    }
+}
@@ -0,0 +1,877 @@
+use std::ops::Range;
+
+use pest::{
+    error::{Error, ErrorVariant, InputLocation},
+    iterators::{Pair, Pairs},
+    Parser,
+};
+use pest_derive::Parser;
+use serde::{Deserialize, Serialize};
+
+use super::spellbook::Spellbook;
+
+#[derive(Parser)]
+#[grammar = "../spellgrammar.pest"]
+struct SpellGrammar;
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Focus {
+    Target,
+    AroundTarget,
+    Me,
+    AroundMe,
+    It,
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Shape {
+    Sword,
+    Person,
+    Arrow,
+    Tree,
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+    Back,
+    TowardMe,
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Modifier {
+    Reverse,
+}
+
+// What a guard can ask about its focus
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Quality {
+    Hot,
+    Cold,
+    Hurt,
+    Near,
+    Far,
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Guard {
+    pub focus: Focus,
+    pub quality: Quality,
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Repeat {
+    Times(u32),
+    Seconds(u32),
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Effect {
+    Lift(Focus),
+    Compress(Focus, Vec<Modifier>),
+    Transform(Focus, Shape),
+    Heat(Focus, Vec<Modifier>),
+    Push(Focus, Direction),
+    Dispel(Focus),
+}
+
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Chain {
+    #[default]
+    Then,
+    And,
+}
+
+// The chain links an effect to the one before it, the first effect in a spell is always `Then`.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainedEffect {
+    pub chain: Chain,
+    // The effect is skipped unless the guard holds when it's reached
+    pub guard: Option<Guard>,
+    pub effect: Effect,
+    pub repeat: Option<Repeat>,
+}
+
+// Each dialect understands everything the one before it does and unlocks more effects. Parsed
+// spells keep the dialect they were written in so saved spellbooks stay readable as new
+// dialects come along.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(into = "u32", try_from = "u32")]
+pub enum Dialect {
+    Apprentice = 1,
+    Adept = 2,
+    Archmage = 3,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpellAst {
+    pub effects: Vec<ChainedEffect>,
+    pub dialect: Dialect,
+}
+
+impl Dialect {
+    pub const LATEST: Dialect = Dialect::Archmage;
+
+    pub fn version(&self) -> u32 {
+        *self as u32
+    }
+
+    // The dialect that unlocks after this one
+    pub fn next(&self) -> Option<Dialect> {
+        Dialect::from_version(self.version() + 1)
+    }
+
+    pub fn from_version(version: u32) -> Option<Dialect> {
+        match version {
+            1 => Some(Dialect::Apprentice),
+            2 => Some(Dialect::Adept),
+            3 => Some(Dialect::Archmage),
+            _ => None,
+        }
+    }
+}
+
+// Saved as the version number so renaming a dialect doesn't break old spellbooks
+impl From<Dialect> for u32 {
+    fn from(dialect: Dialect) -> Self {
+        dialect.version()
+    }
+}
+
+impl TryFrom<u32> for Dialect {
+    type Error = String;
+
+    fn try_from(version: u32) -> Result<Self, Self::Error> {
+        Dialect::from_version(version).ok_or_else(|| format!("Unknown dialect version {version}"))
+    }
+}
+
+impl std::fmt::Display for Dialect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Dialect::Apprentice => write!(f, "apprentice"),
+            Dialect::Adept => write!(f, "adept"),
+            Dialect::Archmage => write!(f, "archmage"),
+        }
+    }
+}
+
+impl Effect {
+    // The first dialect that can speak the effect
+    pub fn dialect(&self) -> Dialect {
+        match self {
+            Effect::Lift(_) | Effect::Heat(_, _) | Effect::Push(_, _) | Effect::Dispel(_) => {
+                Dialect::Apprentice
+            }
+            Effect::Compress(_, _) => Dialect::Adept,
+            Effect::Transform(_, _) => Dialect::Archmage,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExpectedRule {
+    Effect,
+    Focus,
+    Shape,
+    Direction,
+    Modifier,
+    Chain,
+    Quality,
+    Is,
+    Then,
+    Repeat,
+    Word,
+    End,
+}
+
+impl ExpectedRule {
+    fn from_rule(rule: Rule) -> Option<Self> {
+        match rule {
+            Rule::effect
+            | Rule::lift
+            | Rule::compress
+            | Rule::transform
+            | Rule::heat
+            | Rule::push
+            | Rule::dispel
+            | Rule::call
+            | Rule::guard
+            | Rule::body => Some(ExpectedRule::Effect),
+            Rule::focus
+            | Rule::target
+            | Rule::around_target
+            | Rule::me
+            | Rule::around_me
+            | Rule::it => Some(ExpectedRule::Focus),
+            Rule::shape => Some(ExpectedRule::Shape),
+            Rule::direction => Some(ExpectedRule::Direction),
+            Rule::modifier => Some(ExpectedRule::Modifier),
+            Rule::chain => Some(ExpectedRule::Chain),
+            Rule::quality => Some(ExpectedRule::Quality),
+            Rule::guard_is => Some(ExpectedRule::Is),
+            Rule::guard_then => Some(ExpectedRule::Then),
+            Rule::repeat | Rule::times | Rule::seconds | Rule::count => Some(ExpectedRule::Repeat),
+            Rule::word => Some(ExpectedRule::Word),
+            Rule::EOI => Some(ExpectedRule::End),
+            _ => None,
+        }
+    }
+
+    pub fn keywords(&self) -> &'static [&'static str] {
+        match self {
+            ExpectedRule::Effect => &["lift", "compress", "transform", "heat", "push", "dispel"],
+            ExpectedRule::Focus => &["target", "around_target", "me", "around_me", "it"],
+            ExpectedRule::Shape => &["sword", "person", "arrow", "tree"],
+            ExpectedRule::Direction => &["up", "down", "left", "right", "back", "toward_me"],
+            ExpectedRule::Modifier => &["reverse"],
+            ExpectedRule::Chain => &["then", "and"],
+            ExpectedRule::Quality => &["hot", "cold", "hurt", "near", "far"],
+            ExpectedRule::Is => &["is"],
+            ExpectedRule::Then => &["then"],
+            ExpectedRule::Repeat => &["once", "twice", "thrice", "for"],
+            ExpectedRule::Word | ExpectedRule::End => &[],
+        }
+    }
+}
+
+impl std::fmt::Display for ExpectedRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExpectedRule::Effect => write!(f, "an effect"),
+            ExpectedRule::Focus => write!(f, "a focus"),
+            ExpectedRule::Shape => write!(f, "a shape"),
+            ExpectedRule::Direction => write!(f, "a direction"),
+            ExpectedRule::Modifier => write!(f, "a modifier"),
+            ExpectedRule::Chain => write!(f, "'then' or 'and'"),
+            ExpectedRule::Quality => write!(f, "a quality"),
+            ExpectedRule::Is => write!(f, "'is'"),
+            ExpectedRule::Then => write!(f, "'then'"),
+            ExpectedRule::Repeat => write!(f, "a repetition"),
+            ExpectedRule::Word => write!(f, "a new spell word"),
+            ExpectedRule::End => write!(f, "the end of the spell"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpellParseError {
+    pub span: Range<usize>,
+    pub found: String,
+    pub expected: Vec<ExpectedRule>,
+    pub suggestions: Vec<&'static str>,
+    // Set when the spell is fine but uses an effect the dialect hasn't unlocked
+    pub requires: Option<Dialect>,
+    // The words a spell word went through before coming back to itself
+    pub cycle: Vec<String>,
+}
+
+impl SpellParseError {
+    fn from_pest(input: &str, error: Error<Rule>) -> Self {
+        let start = match error.location {
+            InputLocation::Pos(pos) => pos,
+            InputLocation::Span((start, _)) => start,
+        };
+
+        let found = input[start..]
+            .split(char::is_whitespace)
+            .next()
+            .unwrap_or_default()
+            .to_string();
+
+        let mut expected = Vec::new();
+        if let ErrorVariant::ParsingError { positives, .. } = error.variant {
+            for rule in positives.into_iter().filter_map(ExpectedRule::from_rule) {
+                if !expected.contains(&rule) {
+                    expected.push(rule);
+                }
+            }
+        }
+
+        let suggestions = suggest(&found, &expected);
+
+        SpellParseError {
+            span: start..start + found.len(),
+            found,
+            expected,
+            suggestions,
+            requires: None,
+            cycle: Vec::new(),
+        }
+    }
+
+    fn locked(input: &str, span: Range<usize>, requires: Dialect) -> Self {
+        let found = input[span.clone()]
+            .split(char::is_whitespace)
+            .next()
+            .unwrap_or_default()
+            .to_string();
+
+        SpellParseError {
+            span: span.start..span.start + found.len(),
+            found,
+            expected: Vec::new(),
+            suggestions: Vec::new(),
+            requires: Some(requires),
+            cycle: Vec::new(),
+        }
+    }
+
+    fn unknown_word(span: Range<usize>, word: &str) -> Self {
+        let expected = vec![ExpectedRule::Effect];
+
+        SpellParseError {
+            span,
+            found: word.to_string(),
+            suggestions: suggest(word, &expected),
+            expected,
+            requires: None,
+            cycle: Vec::new(),
+        }
+    }
+
+    fn recursive(span: Range<usize>, cycle: Vec<String>) -> Self {
+        SpellParseError {
+            span,
+            found: cycle[0].clone(),
+            expected: Vec::new(),
+            suggestions: Vec::new(),
+            requires: None,
+            cycle,
+        }
+    }
+
+    // Whatever went wrong inside a spell word is reported against the word where it was used
+    fn at_word(mut self, span: Range<usize>, word: &str) -> Self {
+        self.span = span;
+        self.found = word.to_string();
+        self
+    }
+}
+
+impl std::fmt::Display for SpellParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.cycle.is_empty() {
+            return write!(
+                f,
+                "'{}' at {}..{} ends up using itself: {}",
+                self.found,
+                self.span.start,
+                self.span.end,
+                self.cycle.join(" -> ")
+            );
+        }
+
+        if let Some(dialect) = self.requires {
+            return write!(
+                f,
+                "'{}' at {}..{} needs the {} dialect (v{})",
+                self.found,
+                self.span.start,
+                self.span.end,
+                dialect,
+                dialect.version()
+            );
+        }
+
+        if self.found.is_empty() {
+            write!(f, "The spell ends too early")?;
+        } else {
+            write!(
+                f,
+                "Unexpected '{}' at {}..{}",
+                self.found, self.span.start, self.span.end
+            )?;
+        }
+
+        let expected = self
+            .expected
+            .iter()
+            .map(|rule| rule.to_string())
+            .collect::<Vec<String>>();
+        if !expected.is_empty() {
+            write!(f, ", expected {}", expected.join(" or "))?;
+        }
+
+        if !self.suggestions.is_empty() {
+            write!(f, ". Did you mean '{}'?", self.suggestions.join("' or '"))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for SpellParseError {}
+
+// Whether the word is, or is a couple of typos away from, one of the rule's keywords
+pub fn resembles(word: &str, rule: ExpectedRule) -> bool {
+    !suggest(word, &[rule]).is_empty()
+}
+
+// Only keywords within a couple of typos of the word are worth offering
+fn suggest(found: &str, expected: &[ExpectedRule]) -> Vec<&'static str> {
+    if found.is_empty() {
+        return Vec::new();
+    }
+
+    let max_distance = (found.len() / 3).max(1);
+
+    let mut candidates = expected
+        .iter()
+        .flat_map(|rule| rule.keywords().iter().copied())
+        .map(|keyword| (edit_distance(found, keyword), keyword))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect::<Vec<(usize, &'static str)>>();
+
+    candidates.sort();
+    candidates.dedup_by(|a, b| a.1 == b.1);
+    candidates.into_iter().map(|(_, keyword)| keyword).collect()
+}
+
+// Levenshtein distance where swapping two neighbouring letters counts as a single typo
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<char>>();
+    let b = b.chars().collect::<Vec<char>>();
+
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+
+            distances[i][j] = distance;
+        }
+    }
+
+    distances[a.len()][b.len()]
+}
+
+pub fn parse_spell(input: &str) -> Result<SpellAst, SpellParseError> {
+    parse_spell_in(input, Dialect::LATEST)
+}
+
+pub fn parse_spell_in(input: &str, dialect: Dialect) -> Result<SpellAst, SpellParseError> {
+    parse_spell_with(input, dialect, &Spellbook::default())
+}
+
+// Spell words are expanded in place, so the spell that comes out only has the built in effects
+pub fn parse_spell_with(
+    input: &str,
+    dialect: Dialect,
+    spellbook: &Spellbook,
+) -> Result<SpellAst, SpellParseError> {
+    let pairs = SpellGrammar::parse(Rule::spell, input)
+        .map_err(|error| SpellParseError::from_pest(input, error))?;
+
+    let effects = parse_effects(input, pairs, dialect, spellbook, &mut Vec::new())?;
+
+    Ok(SpellAst { effects, dialect })
+}
+
+// Checks "define <word> as <spell>" and returns the word and the spell it stands for
+pub fn parse_definition(
+    input: &str,
+    dialect: Dialect,
+    spellbook: &Spellbook,
+) -> Result<(String, String), SpellParseError> {
+    let mut pairs = SpellGrammar::parse(Rule::definition, input)
+        .map_err(|error| SpellParseError::from_pest(input, error))?;
+
+    let word = pairs
+        .next()
+        .expect("The grammar guarantees a definition names a word")
+        .as_str()
+        .to_string();
+    let body = pairs
+        .next()
+        .expect("The grammar guarantees a definition has a body");
+
+    // The word counts as being expanded already, so anything that leads back to it is caught
+    parse_effects(
+        input,
+        body.clone().into_inner(),
+        dialect,
+        spellbook,
+        &mut vec![word.clone()],
+    )?;
+
+    Ok((word, body.as_str().to_string()))
+}
+
+pub fn is_definition(input: &str) -> bool {
+    input.split_whitespace().next() == Some("define")
+}
+
+fn parse_effects(
+    input: &str,
+    pairs: Pairs<Rule>,
+    dialect: Dialect,
+    spellbook: &Spellbook,
+    expanding: &mut Vec<String>,
+) -> Result<Vec<ChainedEffect>, SpellParseError> {
+    let mut effects = Vec::new();
+    let mut chain = Chain::Then;
+
+    for pair in pairs {
+        match pair.as_rule() {
+            Rule::effect => {
+                let (mut guard, mut verb, mut repeat) = (None, None, None);
+                for part in pair.into_inner() {
+                    match part.as_rule() {
+                        Rule::guard => guard = Some(parse_guard(part)),
+                        Rule::repeat => repeat = Some(parse_repeat(part)),
+                        _ => verb = Some(part),
+                    }
+                }
+
+                let verb = verb.expect("The grammar guarantees an effect has a verb");
+                let span = verb.as_span().start()..verb.as_span().end();
+
+                if verb.as_rule() == Rule::call {
+                    let word = verb.as_str();
+                    let expanded = expand(word, span.clone(), dialect, spellbook, expanding)?;
+
+                    // The chain in front of the word joins its first effect to the spell
+                    for (index, mut chained) in expanded.into_iter().enumerate() {
+                        if index == 0 {
+                            chained.chain = chain;
+                        }
+                        effects.push(chained);
+                    }
+                    continue;
+                }
+
+                let effect = parse_effect(verb);
+
+                if effect.dialect() > dialect {
+                    return Err(SpellParseError::locked(input, span, effect.dialect()));
+                }
+
+                effects.push(ChainedEffect {
+                    chain,
+                    guard,
+                    effect,
+                    repeat,
+                });
+            }
+            Rule::chain => chain = parse_chain(pair),
+            _ => continue,
+        }
+    }
+
+    Ok(effects)
+}
+
+fn expand(
+    word: &str,
+    span: Range<usize>,
+    dialect: Dialect,
+    spellbook: &Spellbook,
+    expanding: &mut Vec<String>,
+) -> Result<Vec<ChainedEffect>, SpellParseError> {
+    if let Some(start) = expanding.iter().position(|expanded| expanded == word) {
+        let mut cycle = expanding[start..].to_vec();
+        cycle.push(word.to_string());
+        return Err(SpellParseError::recursive(span, cycle));
+    }
+
+    let Some(definition) = spellbook.get(word) else {
+        return Err(SpellParseError::unknown_word(span, word));
+    };
+
+    // A word never does more than whoever wrote it could say
+    let dialect = dialect.min(definition.dialect);
+
+    let pairs = SpellGrammar::parse(Rule::spell, &definition.body)
+        .map_err(|error| SpellParseError::from_pest(&definition.body, error))
+        .map_err(|error| error.at_word(span.clone(), word))?;
+
+    expanding.push(word.to_string());
+    let expanded = parse_effects(&definition.body, pairs, dialect, spellbook, expanding);
+    expanding.pop();
+
+    expanded.map_err(|error| error.at_word(span, word))
+}
+
+fn parse_effect(effect: Pair<Rule>) -> Effect {
+    let rule = effect.as_rule();
+    let mut inner = effect.into_inner();
+
+    let focus = parse_focus(
+        inner
+            .next()
+            .expect("The grammar guarantees every verb has a focus"),
+    );
+
+    match rule {
+        Rule::lift => Effect::Lift(focus),
+        Rule::compress => Effect::Compress(focus, inner.map(parse_modifier).collect()),
+        Rule::transform => {
+            let shape = parse_shape(
+                inner
+                    .next()
+                    .expect("The grammar guarantees transform has a shape"),
+            );
+            Effect::Transform(focus, shape)
+        }
+        Rule::heat => Effect::Heat(focus, inner.map(parse_modifier).collect()),
+        Rule::push => {
+            let direction = parse_direction(
+                inner
+                    .next()
+                    .expect("The grammar guarantees push has a direction"),
+            );
+            Effect::Push(focus, direction)
+        }
+        Rule::dispel => Effect::Dispel(focus),
+        _ => unreachable!("Unexpected effect rule {rule:?}"),
+    }
+}
+
+fn parse_focus(pair: Pair<Rule>) -> Focus {
+    let keyword = pair
+        .into_inner()
+        .next()
+        .expect("The grammar guarantees a focus wraps a single keyword");
+
+    match keyword.as_rule() {
+        Rule::target => Focus::Target,
+        Rule::around_target => Focus::AroundTarget,
+        Rule::me => Focus::Me,
+        Rule::around_me => Focus::AroundMe,
+        Rule::it => Focus::It,
+        rule => unreachable!("Unexpected focus rule {rule:?}"),
+    }
+}
+
+fn parse_guard(pair: Pair<Rule>) -> Guard {
+    let mut inner = pair.into_inner();
+
+    let focus = parse_focus(
+        inner
+            .next()
+            .expect("The grammar guarantees a guard has a focus"),
+    );
+    let quality = inner
+        .find(|part| part.as_rule() == Rule::quality)
+        .expect("The grammar guarantees a guard has a quality");
+
+    let quality = match quality.as_str() {
+        "hot" => Quality::Hot,
+        "cold" => Quality::Cold,
+        "hurt" => Quality::Hurt,
+        "near" => Quality::Near,
+        "far" => Quality::Far,
+        quality => unreachable!("Unexpected quality {quality}"),
+    };
+
+    Guard { focus, quality }
+}
+
+fn parse_repeat(pair: Pair<Rule>) -> Repeat {
+    let repeat = pair
+        .into_inner()
+        .next()
+        .expect("The grammar guarantees a repeat wraps times or seconds");
+    let rule = repeat.as_rule();
+
+    // Counts too big to fit just make the spell too expensive to cast
+    let count = repeat
+        .clone()
+        .into_inner()
+        .next()
+        .map(|count| count.as_str().parse().unwrap_or(u32::MAX));
+
+    match (rule, repeat.as_str(), count) {
+        (Rule::seconds, _, Some(count)) => Repeat::Seconds(count),
+        (Rule::times, _, Some(count)) => Repeat::Times(count),
+        (Rule::times, "once", None) => Repeat::Times(1),
+        (Rule::times, "twice", None) => Repeat::Times(2),
+        (Rule::times, "thrice", None) => Repeat::Times(3),
+        (_, repeat, _) => unreachable!("Unexpected repeat {repeat}"),
+    }
+}
+
+fn parse_shape(pair: Pair<Rule>) -> Shape {
+    match pair.as_str() {
+        "sword" => Shape::Sword,
+        "person" => Shape::Person,
+        "arrow" => Shape::Arrow,
+        "tree" => Shape::Tree,
+        shape => unreachable!("Unexpected shape {shape}"),
+    }
+}
+
+fn parse_direction(pair: Pair<Rule>) -> Direction {
+    match pair.as_str() {
+        "up" => Direction::Up,
+        "down" => Direction::Down,
+        "left" => Direction::Left,
+        "right" => Direction::Right,
+        "back" => Direction::Back,
+        "toward_me" => Direction::TowardMe,
+        direction => unreachable!("Unexpected direction {direction}"),
+    }
+}
+
+fn parse_modifier(pair: Pair<Rule>) -> Modifier {
+    match pair.as_str() {
+        "reverse" => Modifier::Reverse,
+        modifier => unreachable!("Unexpected modifier {modifier}"),
+    }
+}
+
+fn parse_chain(pair: Pair<Rule>) -> Chain {
+    match pair.as_str() {
+        "then" => Chain::Then,
+        "and" => Chain::And,
+        chain => unreachable!("Unexpected chain {chain}"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_chained_spell() {
+        let ast = parse_spell("heat target reverse then lift me").unwrap();
+
+        assert_eq!(
+            ast.effects,
+            vec![
+                ChainedEffect {
+                    chain: Chain::Then,
+                    guard: None,
+                    effect: Effect::Heat(Focus::Target, vec![Modifier::Reverse]),
+                    repeat: None,
+                },
+                ChainedEffect {
+                    chain: Chain::Then,
+                    guard: None,
+                    effect: Effect::Lift(Focus::Me),
+                    repeat: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_transform_and_concurrent_chain() {
+        let ast = parse_spell("compress around_me and transform it into sword").unwrap();
+
+        assert_eq!(
+            ast.effects[0].effect,
+            Effect::Compress(Focus::AroundMe, vec![])
+        );
+        assert_eq!(ast.effects[1].chain, Chain::And);
+        assert_eq!(
+            ast.effects[1].effect,
+            Effect::Transform(Focus::It, Shape::Sword)
+        );
+    }
+
+    #[test]
+    fn locks_effects_behind_dialects() {
+        let ast = parse_spell_in("heat target then push it back", Dialect::Apprentice).unwrap();
+        assert_eq!(ast.dialect, Dialect::Apprentice);
+        assert_eq!(parse_spell("lift me").unwrap().dialect, Dialect::LATEST);
+
+        let error =
+            parse_spell_in("lift me and transform it into tree", Dialect::Adept).unwrap_err();
+        assert_eq!(error.requires, Some(Dialect::Archmage));
+        assert_eq!(error.found, "transform");
+        assert_eq!(error.span, 12..21);
+    }
+
+    #[test]
+    fn parses_push_with_direction() {
+        let ast = parse_spell("heat target then push target back").unwrap();
+
+        assert_eq!(
+            ast.effects[1].effect,
+            Effect::Push(Focus::Target, Direction::Back)
+        );
+
+        let error = parse_spell("push me sideways").unwrap_err();
+        assert_eq!(error.expected, vec![ExpectedRule::Direction]);
+    }
+
+    #[test]
+    fn rejects_unknown_words() {
+        let error = parse_spell("heat target sideways").unwrap_err();
+
+        assert_eq!(error.span, 12..20);
+        assert_eq!(error.found, "sideways");
+        assert!(error.expected.contains(&ExpectedRule::Modifier));
+        assert!(error.expected.contains(&ExpectedRule::Chain));
+        assert!(error.suggestions.is_empty());
+    }
+
+    #[test]
+    fn suggests_keywords_for_misspellings() {
+        let error = parse_spell("lift me then tranform targte into sword").unwrap_err();
+
+        assert_eq!(error.span, 13..21);
+        assert_eq!(error.expected, vec![ExpectedRule::Effect]);
+        assert_eq!(error.suggestions, vec!["transform"]);
+
+        let error = parse_spell("transform me into swrod").unwrap_err();
+
+        assert_eq!(error.expected, vec![ExpectedRule::Shape]);
+        assert_eq!(error.suggestions, vec!["sword"]);
+    }
+
+    #[test]
+    fn parses_guards_and_repeats() {
+        let ast = parse_spell("if target is hot then compress it and lift target thrice").unwrap();
+
+        assert_eq!(
+            ast.effects[0].guard,
+            Some(Guard {
+                focus: Focus::Target,
+                quality: Quality::Hot
+            })
+        );
+        assert_eq!(ast.effects[0].effect, Effect::Compress(Focus::It, vec![]));
+        assert_eq!(ast.effects[1].chain, Chain::And);
+        assert_eq!(ast.effects[1].repeat, Some(Repeat::Times(3)));
+
+        let ast = parse_spell("heat around_me reverse for 3 seconds then lift me 4 times").unwrap();
+        assert_eq!(
+            ast.effects[0].effect,
+            Effect::Heat(Focus::AroundMe, vec![Modifier::Reverse])
+        );
+        assert_eq!(ast.effects[0].repeat, Some(Repeat::Seconds(3)));
+        assert_eq!(ast.effects[1].repeat, Some(Repeat::Times(4)));
+
+        let error = parse_spell("if target is hot compress it").unwrap_err();
+        assert_eq!(error.expected, vec![ExpectedRule::Then]);
+        assert_eq!(error.span, 17..25);
+    }
+
+    #[test]
+    fn reports_early_end() {
+        let error = parse_spell("heat me then").unwrap_err();
+
+        assert_eq!(error.span, 12..12);
+        assert!(error.found.is_empty());
+        assert_eq!(error.expected, vec![ExpectedRule::Effect]);
+    }
+}
@@ -0,0 +1,166 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::{With, Without},
+        system::{Commands, Query, Res},
+        world::World,
+    },
+    math::Vec2,
+    time::Time,
+    transform::components::Transform,
+};
+use bevy_ecs_tilemap::prelude::{TilePos, TileStorage, TilemapGridSize, TilemapType};
+
+use crate::{
+    agent,
+    terrain::{tile_at, Blocking, MapLayer},
+};
+
+use super::parser::Direction;
+
+const PUSH_SPEED: f32 = 600.0;
+
+// Fraction of the knockback speed lost every second
+const FRICTION: f32 = 4.0;
+const STOP_SPEED: f32 = 10.0;
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Knockback {
+    pub velocity: Vec2,
+}
+
+impl Knockback {
+    pub fn new(heading: Vec2) -> Self {
+        Knockback {
+            velocity: heading.normalize_or_zero() * PUSH_SPEED,
+        }
+    }
+
+    // Along the map's own axes, a step of x or y tiles, which on an isometric map run diagonally
+    // across the screen
+    pub fn along_grid(step: Vec2, grid_size: &TilemapGridSize, map_type: &TilemapType) -> Self {
+        let centre = TilePos { x: 1, y: 1 }.center_in_world(grid_size, map_type);
+        let x_axis = TilePos { x: 2, y: 1 }.center_in_world(grid_size, map_type) - centre;
+        let y_axis = TilePos { x: 1, y: 2 }.center_in_world(grid_size, map_type) - centre;
+
+        Knockback::new(x_axis * step.x + y_axis * step.y)
+    }
+}
+
+impl Direction {
+    // Back and toward_me are relative to whoever cast the spell. Away from any map, up/down/left/right
+    // follow the same screen axes as walking.
+    pub fn heading(&self, position: Vec2, caster: Option<Vec2>) -> Option<Vec2> {
+        let heading = match self {
+            Direction::Up => agent::Direction::N.as_vec(),
+            Direction::Down => agent::Direction::S.as_vec(),
+            Direction::Left => agent::Direction::W.as_vec(),
+            Direction::Right => agent::Direction::E.as_vec(),
+            Direction::Back => position - caster?,
+            Direction::TowardMe => caster? - position,
+        };
+
+        heading.try_normalize()
+    }
+
+    // On a map, up/down/left/right step along its rows and columns instead, so whatever's pushed
+    // slides down a line of tiles
+    fn grid_step(&self) -> Option<Vec2> {
+        match self {
+            Direction::Up => Some(Vec2::Y),
+            Direction::Down => Some(-Vec2::Y),
+            Direction::Left => Some(-Vec2::X),
+            Direction::Right => Some(Vec2::X),
+            Direction::Back | Direction::TowardMe => None,
+        }
+    }
+}
+
+pub fn knockback(world: &mut World, direction: Direction, heading: Vec2) -> Knockback {
+    let mut maps = world.query::<(&TilemapGridSize, &TilemapType)>();
+
+    match (direction.grid_step(), maps.iter(world).next()) {
+        (Some(step), Some((grid_size, map_type))) => {
+            Knockback::along_grid(step, grid_size, map_type)
+        }
+        _ => Knockback::new(heading),
+    }
+}
+
+pub fn apply_knockback(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Knockback, &mut Transform), Without<TileStorage>>,
+    layers: Query<MapLayer>,
+    blocking: Query<(), With<Blocking>>,
+) {
+    let delta = time.delta_seconds();
+
+    for (entity, mut knockback, mut transform) in &mut query {
+        let next = transform.translation.truncate() + knockback.velocity * delta;
+
+        let blocked = layers
+            .iter()
+            .filter_map(|layer| tile_at(layer, next))
+            .any(|tile| blocking.contains(tile));
+
+        if blocked {
+            commands.entity(entity).remove::<Knockback>();
+            continue;
+        }
+
+        transform.translation.x = next.x;
+        transform.translation.y = next.y;
+
+        knockback.velocity *= (1.0 - FRICTION * delta).max(0.0);
+        if knockback.velocity.length() < STOP_SPEED {
+            commands.entity(entity).remove::<Knockback>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bevy_ecs_tilemap::prelude::IsoCoordSystem;
+
+    #[test]
+    fn relative_directions_use_the_caster() {
+        let position = Vec2::new(10.0, 0.0);
+        let caster = Some(Vec2::ZERO);
+
+        assert_eq!(Direction::Back.heading(position, caster), Some(Vec2::X));
+        assert_eq!(
+            Direction::TowardMe.heading(position, caster),
+            Some(-Vec2::X)
+        );
+        assert_eq!(Direction::Back.heading(position, None), None);
+        assert_eq!(Direction::Up.heading(position, None), Some(Vec2::Y));
+    }
+
+    #[test]
+    fn grid_pushes_follow_the_isometric_axes() {
+        let grid_size = TilemapGridSize { x: 64.0, y: 32.0 };
+        let isometric = TilemapType::Isometric(IsoCoordSystem::Diamond);
+
+        let along_x = Knockback::along_grid(Vec2::X, &grid_size, &isometric);
+        assert_eq!(
+            along_x.velocity,
+            Vec2::new(32.0, -16.0).normalize() * PUSH_SPEED
+        );
+
+        let along_y = Knockback::along_grid(Vec2::Y, &grid_size, &TilemapType::Square);
+        assert_eq!(along_y.velocity, Vec2::Y * PUSH_SPEED);
+
+        let mut world = World::new();
+        let pushed = knockback(&mut world, Direction::Right, Vec2::X);
+        assert_eq!(pushed.velocity, Vec2::X * PUSH_SPEED);
+
+        world.spawn((grid_size, isometric));
+        let pushed = knockback(&mut world, Direction::Right, Vec2::X);
+        assert_eq!(pushed.velocity, along_x.velocity);
+        let pushed = knockback(&mut world, Direction::Back, Vec2::X);
+        assert_eq!(pushed.velocity, Vec2::X * PUSH_SPEED);
+    }
+}
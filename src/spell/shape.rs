@@ -0,0 +1,317 @@
+use bevy::{
+    asset::Handle,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::With,
+        system::{Commands, Query, Res},
+        world::{Mut, World},
+    },
+    hierarchy::{
+        despawn_with_children_recursive, BuildWorldChildren, Children, DespawnRecursiveExt,
+    },
+    math::{Quat, Vec2, Vec3},
+    prelude::default,
+    sprite::{SpriteSheetBundle, TextureAtlas, TextureAtlasSprite},
+    time::{Time, Timer, TimerMode},
+    transform::components::Transform,
+};
+
+use crate::{
+    agent::{human::HumanController, npc::AiController, AnimationSet, CharacterState},
+    AnimationTimer, EntityFactory, Game,
+};
+
+use super::{
+    interaction::ObjectKind, lift::Shadow, parser::Shape, status::StatusOverlay, BlobState,
+};
+
+pub const SWORD_SPRITE: usize = 6;
+pub const ARROW_SPRITE: usize = 0;
+pub const TREE_SPRITE: usize = 0;
+
+const PICKUP_RADIUS: f32 = 32.0;
+const ARROW_SPEED: f32 = 400.0;
+
+#[derive(Component)]
+pub struct Pickup;
+
+#[derive(Component)]
+pub struct Projectile {
+    pub velocity: Vec2,
+    pub lifetime: Timer,
+}
+
+#[derive(Component)]
+pub struct Obstacle {
+    pub radius: f32,
+}
+
+pub type PickupBundle = (SpriteSheetBundle, Pickup);
+pub type ProjectileBundle = (SpriteSheetBundle, Projectile);
+pub type ObstacleBundle = (SpriteSheetBundle, Obstacle);
+
+// Everything that makes an entity one shape rather than another, apart from who controls it
+type ShapeComponents = (
+    AiController,
+    CharacterState,
+    AnimationSet,
+    AnimationTimer,
+    BlobState,
+    Pickup,
+    Projectile,
+    Obstacle,
+);
+
+pub fn new_pickup_bundle(atlas_handle: Handle<TextureAtlas>, index: usize) -> PickupBundle {
+    (
+        SpriteSheetBundle {
+            texture_atlas: atlas_handle,
+            sprite: TextureAtlasSprite::new(index),
+            transform: Transform::from_scale(Vec3::splat(2.0))
+                .with_translation(Vec3::new(400.0, 10.0, 10.00)),
+            ..default()
+        },
+        Pickup,
+    )
+}
+
+pub fn new_projectile_bundle(atlas_handle: Handle<TextureAtlas>, index: usize) -> ProjectileBundle {
+    (
+        SpriteSheetBundle {
+            texture_atlas: atlas_handle,
+            sprite: TextureAtlasSprite::new(index),
+            transform: Transform::from_scale(Vec3::splat(2.0))
+                .with_translation(Vec3::new(400.0, 10.0, 10.00)),
+            ..default()
+        },
+        Projectile {
+            velocity: Vec2::Y * ARROW_SPEED,
+            lifetime: Timer::from_seconds(3.0, TimerMode::Once),
+        },
+    )
+}
+
+pub fn new_obstacle_bundle(atlas_handle: Handle<TextureAtlas>, index: usize) -> ObstacleBundle {
+    (
+        SpriteSheetBundle {
+            texture_atlas: atlas_handle,
+            sprite: TextureAtlasSprite::new(index),
+            transform: Transform::from_translation(Vec3::new(400.0, 10.0, 10.00)),
+            ..default()
+        },
+        Obstacle { radius: 40.0 },
+    )
+}
+
+pub fn place(transform: &mut Transform, position: Vec2) {
+    transform.translation.x = position.x;
+    transform.translation.y = position.y;
+}
+
+// Swaps the sprite and behaviour of an existing entity in place, so anything still holding
+// on to the entity (a selection, `it`) now points at the new shape. Lifts and statuses carry
+// over to the new shape.
+pub fn transform_into(
+    commands: &mut Commands,
+    entity: Entity,
+    shape: Shape,
+    position: Vec2,
+    heading: Vec2,
+) {
+    commands.add(move |world: &mut World| {
+        if world.get_entity(entity).is_none() {
+            return;
+        }
+
+        world.resource_scope(|world, game: Mut<Game>| {
+            let Some(entity_factory) = game.entity_factory.as_ref() else {
+                return;
+            };
+            reshape(world, entity_factory, entity, shape, position, heading);
+        });
+    });
+}
+
+fn reshape(
+    world: &mut World,
+    entity_factory: &EntityFactory,
+    entity: Entity,
+    shape: Shape,
+    position: Vec2,
+    heading: Vec2,
+) {
+    // The player stays in control of themselves, speech bubble and all, as a person
+    let keeps_control = shape == Shape::Person && world.get::<HumanController>(entity).is_some();
+
+    let children = world
+        .get::<Children>(entity)
+        .map(|children| children.to_vec())
+        .unwrap_or_default();
+    for child in children {
+        let carried = keeps_control
+            || world.get::<Shadow>(child).is_some()
+            || world.get::<StatusOverlay>(child).is_some();
+        if !carried {
+            despawn_with_children_recursive(world, child);
+        }
+    }
+
+    let mut target = world.entity_mut(entity);
+    target.remove::<ShapeComponents>();
+    if !keeps_control {
+        target.remove::<HumanController>();
+    }
+
+    match shape {
+        Shape::Sword => {
+            let mut bundle = entity_factory.make_sword();
+            place(&mut bundle.0.transform, position);
+            target.insert((bundle, ObjectKind::Sword));
+        }
+        Shape::Person if keeps_control => {
+            let (mut sprite, animations, timer, state, ..) = entity_factory.make_human().0;
+            place(&mut sprite.transform, position);
+            target.insert((sprite, animations, timer, state, ObjectKind::Person));
+        }
+        Shape::Person => {
+            let (
+                (
+                    mut sprite,
+                    animations,
+                    timer,
+                    state,
+                    _,
+                    thermal,
+                    health,
+                    mana,
+                    cooldowns,
+                    kind,
+                    fluency,
+                    spellbook,
+                ),
+                text,
+            ) = entity_factory.make_ai();
+            place(&mut sprite.transform, position);
+            // Without an AI controller it stands where it was made rather than going on patrol
+            target
+                .insert((sprite, animations, timer, state, thermal, health))
+                .insert((mana, cooldowns, kind, fluency, spellbook))
+                .with_children(|parent| {
+                    parent.spawn(text);
+                });
+        }
+        Shape::Arrow => {
+            let heading = heading.try_normalize().unwrap_or(Vec2::Y);
+            let mut bundle = entity_factory.make_arrow();
+            place(&mut bundle.0.transform, position);
+            bundle.0.transform.rotation = Quat::from_rotation_z(Vec2::Y.angle_between(heading));
+            bundle.1.velocity = heading * ARROW_SPEED;
+            target.insert((bundle, ObjectKind::Arrow));
+        }
+        Shape::Tree => {
+            let mut bundle = entity_factory.make_tree();
+            place(&mut bundle.0.transform, position);
+            target.insert((bundle, ObjectKind::Tree));
+        }
+    }
+}
+
+pub fn move_projectiles(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Projectile, &mut Transform)>,
+) {
+    for (entity, mut projectile, mut transform) in &mut query {
+        projectile.lifetime.tick(time.delta());
+        if projectile.lifetime.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let step = projectile.velocity * time.delta_seconds();
+        transform.translation += step.extend(0.0);
+    }
+}
+
+pub fn collect_pickups(
+    mut commands: Commands,
+    player_query: Query<&Transform, With<HumanController>>,
+    pickups: Query<(Entity, &Transform), With<Pickup>>,
+) {
+    for player_transform in player_query.iter() {
+        let player_position = player_transform.translation.truncate();
+
+        for (entity, transform) in pickups.iter() {
+            if transform.translation.truncate().distance(player_position) <= PICKUP_RADIUS {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use bevy::{
+        asset::Assets,
+        ecs::{
+            event::Events,
+            system::{CommandQueue, IntoSystem, ResMut, System, SystemState},
+        },
+        prelude::default,
+    };
+
+    use super::*;
+    use crate::{
+        spell::lift::{fall_lifted, lift, Landed, Lifted},
+        NamedAssets,
+    };
+
+    #[test]
+    fn lifted_things_land_after_being_transformed() {
+        let mut world = World::new();
+        world.init_resource::<Assets<TextureAtlas>>();
+        world.init_resource::<Time>();
+        world.init_resource::<Events<Landed>>();
+
+        let mut atlases = SystemState::<ResMut<Assets<TextureAtlas>>>::new(&mut world);
+        let assets = NamedAssets {
+            character: default(),
+            fire: default(),
+            rock: default(),
+            items: default(),
+            trees: default(),
+            font: default(),
+        };
+        let entity_factory = EntityFactory::new(assets, atlases.get_mut(&mut world));
+        let rock = world.spawn(entity_factory.make_rock()).id();
+        world.insert_resource(Game::offline(entity_factory));
+
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        lift(&mut commands, rock);
+        transform_into(&mut commands, rock, Shape::Sword, Vec2::ZERO, Vec2::Y);
+        queue.apply(&mut world);
+
+        assert!(world.get::<Pickup>(rock).is_some());
+        assert!(world.get::<Lifted>(rock).is_some());
+        let shadow = world.get::<Children>(rock).unwrap()[0];
+        assert!(world.get::<Shadow>(shadow).is_some());
+
+        let mut fall = IntoSystem::into_system(fall_lifted);
+        fall.initialize(&mut world);
+        for _ in 0..100 {
+            world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_millis(100));
+            fall.run((), &mut world);
+            fall.apply_deferred(&mut world);
+        }
+
+        assert!(world.get::<Lifted>(rock).is_none());
+        assert_eq!(world.resource::<Events<Landed>>().len(), 1);
+        assert!(world.get_entity(shadow).is_none());
+    }
+}
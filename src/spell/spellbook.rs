@@ -0,0 +1,114 @@
+use std::{collections::BTreeMap, path::Path};
+
+use bevy::ecs::component::Component;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::parser::{parse_definition, Dialect, SpellParseError};
+
+// Where the player's own spell words are kept between games
+pub const PLAYER_SPELLBOOK: &str = "spellbook.ron";
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Definition {
+    pub body: String,
+    // The dialect the word was defined in
+    pub dialect: Dialect,
+}
+
+// The spell words a caster has defined, each one stands in for the spell it was defined as
+#[derive(Component, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Spellbook {
+    words: BTreeMap<String, Definition>,
+}
+
+#[derive(Debug, Error)]
+pub enum SpellbookError {
+    #[error("Could not access spellbook: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not read spellbook: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("Could not write spellbook: {0}")]
+    Write(#[from] ron::Error),
+}
+
+impl Spellbook {
+    pub fn get(&self, word: &str) -> Option<&Definition> {
+        self.words.get(word)
+    }
+
+    // Takes "define <word> as <spell>", a word that's already there gets the new meaning
+    pub fn define(&mut self, input: &str, dialect: Dialect) -> Result<String, SpellParseError> {
+        let (word, body) = parse_definition(input, dialect, self)?;
+
+        self.words
+            .insert(word.clone(), Definition { body, dialect });
+
+        Ok(word)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SpellbookError> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(ron::de::from_str(&text)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SpellbookError> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::spell::parser::{parse_spell_with, Chain, Direction, Effect, ExpectedRule, Focus};
+
+    #[test]
+    fn expands_words_and_rejects_recursion() {
+        let mut spellbook = Spellbook::default();
+        spellbook
+            .define(
+                "define fireball as heat target then push it back",
+                Dialect::LATEST,
+            )
+            .unwrap();
+        spellbook
+            .define("define volley as fireball and lift me", Dialect::LATEST)
+            .unwrap();
+
+        let ast = parse_spell_with("lift me and volley", Dialect::LATEST, &spellbook).unwrap();
+        let effects = ast
+            .effects
+            .iter()
+            .map(|chained| (chained.chain, chained.effect.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            effects,
+            vec![
+                (Chain::Then, Effect::Lift(Focus::Me)),
+                (Chain::And, Effect::Heat(Focus::Target, vec![])),
+                (Chain::Then, Effect::Push(Focus::It, Direction::Back)),
+                (Chain::And, Effect::Lift(Focus::Me)),
+            ]
+        );
+
+        let error = spellbook
+            .define("define fireball as volley", Dialect::LATEST)
+            .unwrap_err();
+        assert_eq!(error.cycle, vec!["fireball", "volley", "fireball"]);
+        assert_eq!(error.span, 19..25);
+        assert_eq!(
+            spellbook.get("fireball").unwrap().body,
+            "heat target then push it back"
+        );
+
+        let error = spellbook
+            .define("define lift as heat me", Dialect::LATEST)
+            .unwrap_err();
+        assert_eq!(error.expected, vec![ExpectedRule::Word]);
+
+        let saved = ron::ser::to_string(&spellbook).unwrap();
+        assert_eq!(ron::de::from_str::<Spellbook>(&saved).unwrap(), spellbook);
+    }
+}
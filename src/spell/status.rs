@@ -0,0 +1,348 @@
+use std::time::Duration;
+
+use bevy::{
+    asset::Handle,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::EventWriter,
+        query::{With, Without},
+        system::{Commands, Query, Res},
+        world::World,
+    },
+    hierarchy::{BuildWorldChildren, DespawnRecursiveExt},
+    math::Vec3,
+    prelude::default,
+    render::color::Color,
+    sprite::{SpriteSheetBundle, TextureAtlas, TextureAtlasSprite},
+    time::{Time, Timer, TimerMode},
+    transform::components::Transform,
+};
+
+use crate::{
+    agent::{Action, CharacterState, Damaged, Health},
+    EntityFactory,
+};
+
+use super::{
+    lift::{Lifted, LIFT_DURATION},
+    temperature::{Temperature, FREEZING_POINT},
+};
+
+const BURN_DAMAGE: f32 = 2.0;
+const BURN_INTERVAL: Duration = Duration::from_secs(1);
+
+// What happens when a status lands on something that already has it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stacking {
+    // Starts the timer over
+    Refresh,
+    // Adds a stack, up to the limit, and starts the timer over
+    Stack { max: u32 },
+    // Leaves the one that's already running alone
+    Ignore,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StatusKind {
+    Burning,
+    Levitating,
+    Frozen,
+    Compressed,
+}
+
+// Marks the sprite drawn over something to show it has a status, so it isn't mistaken for
+// something that can be targeted itself
+#[derive(Component)]
+pub struct StatusOverlay;
+
+pub type StatusOverlayBundle = (SpriteSheetBundle, StatusOverlay);
+
+pub fn new_status_overlay_bundle(
+    atlas_handle: Handle<TextureAtlas>,
+    index: usize,
+    color: Color,
+) -> StatusOverlayBundle {
+    (
+        SpriteSheetBundle {
+            texture_atlas: atlas_handle,
+            sprite: TextureAtlasSprite {
+                index,
+                color,
+                ..default()
+            },
+            // Just in front of whatever it's attached to
+            transform: Transform::from_translation(Vec3::new(0.0, 0.0, 0.1)),
+            ..default()
+        },
+        StatusOverlay,
+    )
+}
+
+// The part every status has in common
+#[derive(Debug)]
+pub struct Status {
+    pub remaining: Timer,
+    pub stacks: u32,
+    // Mana spent on the strongest spell that laid it, a dispel needs at least as much to lift it
+    pub power: f32,
+    overlay: Entity,
+}
+
+impl Status {
+    fn new(duration: Duration, power: f32, overlay: Entity) -> Self {
+        Status {
+            remaining: Timer::new(duration, TimerMode::Once),
+            stacks: 1,
+            power,
+            overlay,
+        }
+    }
+
+    fn reapply(&mut self, stacking: Stacking, power: f32) {
+        if stacking != Stacking::Ignore {
+            self.power = self.power.max(power);
+        }
+
+        match stacking {
+            Stacking::Refresh => self.remaining.reset(),
+            Stacking::Stack { max } => {
+                self.stacks = (self.stacks + 1).min(max);
+                self.remaining.reset();
+            }
+            Stacking::Ignore => {}
+        }
+    }
+}
+
+pub trait StatusEffect: Component {
+    const KIND: StatusKind;
+    const DURATION: Duration;
+    const STACKING: Stacking;
+
+    fn new(status: Status) -> Self;
+    fn status_mut(&mut self) -> &mut Status;
+}
+
+// Takes damage every so often, more for every stack
+#[derive(Component, Debug)]
+pub struct Burning {
+    pub status: Status,
+    next_burn: Timer,
+}
+
+impl StatusEffect for Burning {
+    const KIND: StatusKind = StatusKind::Burning;
+    const DURATION: Duration = Duration::from_secs(4);
+    const STACKING: Stacking = Stacking::Stack { max: 3 };
+
+    fn new(status: Status) -> Self {
+        Burning {
+            status,
+            next_burn: Timer::new(BURN_INTERVAL, TimerMode::Repeating),
+        }
+    }
+
+    fn status_mut(&mut self) -> &mut Status {
+        &mut self.status
+    }
+}
+
+// Lasts as long as the lift holds, and no longer than it stays off the ground
+#[derive(Component, Debug)]
+pub struct Levitating(pub Status);
+
+impl StatusEffect for Levitating {
+    const KIND: StatusKind = StatusKind::Levitating;
+    const DURATION: Duration = LIFT_DURATION;
+    const STACKING: Stacking = Stacking::Refresh;
+
+    fn new(status: Status) -> Self {
+        Levitating(status)
+    }
+
+    fn status_mut(&mut self) -> &mut Status {
+        &mut self.0
+    }
+}
+
+// Held at freezing, which is what stops it moving
+#[derive(Component, Debug)]
+pub struct Frozen(pub Status);
+
+impl StatusEffect for Frozen {
+    const KIND: StatusKind = StatusKind::Frozen;
+    const DURATION: Duration = Duration::from_secs(5);
+    const STACKING: Stacking = Stacking::Refresh;
+
+    fn new(status: Status) -> Self {
+        Frozen(status)
+    }
+
+    fn status_mut(&mut self) -> &mut Status {
+        &mut self.0
+    }
+}
+
+// Squeezed too tight to run, squeezing harder doesn't make it last longer
+#[derive(Component, Debug)]
+pub struct Compressed(pub Status);
+
+impl StatusEffect for Compressed {
+    const KIND: StatusKind = StatusKind::Compressed;
+    const DURATION: Duration = Duration::from_secs(3);
+    const STACKING: Stacking = Stacking::Ignore;
+
+    fn new(status: Status) -> Self {
+        Compressed(status)
+    }
+
+    fn status_mut(&mut self) -> &mut Status {
+        &mut self.0
+    }
+}
+
+pub fn afflict<S: StatusEffect>(
+    commands: &mut Commands,
+    entity_factory: &EntityFactory,
+    entity: Entity,
+    power: f32,
+) {
+    let overlay = entity_factory.make_status_overlay(S::KIND);
+
+    commands.add(move |world: &mut World| {
+        let Some(mut target) = world.get_entity_mut(entity) else {
+            return;
+        };
+
+        if let Some(mut status) = target.get_mut::<S>() {
+            status.status_mut().reapply(S::STACKING, power);
+            return;
+        }
+
+        let overlay = world.spawn(overlay).id();
+        world
+            .entity_mut(entity)
+            .add_child(overlay)
+            .insert(S::new(Status::new(S::DURATION, power, overlay)));
+    });
+}
+
+// Lifts every status on the entity that no more mana went into than the dispel had behind it
+pub fn cleanse(world: &mut World, entity: Entity, power: f32) {
+    cleanse_status::<Burning>(world, entity, power);
+    cleanse_status::<Levitating>(world, entity, power);
+    cleanse_status::<Frozen>(world, entity, power);
+    cleanse_status::<Compressed>(world, entity, power);
+}
+
+fn cleanse_status<S: StatusEffect>(world: &mut World, entity: Entity, power: f32) {
+    let Some(mut target) = world.get_entity_mut(entity) else {
+        return;
+    };
+
+    let Some(mut status) = target.get_mut::<S>() else {
+        return;
+    };
+
+    let status = status.status_mut();
+    if status.power > power {
+        return;
+    }
+    let overlay = status.overlay;
+
+    target.remove::<S>();
+    if let Some(overlay) = world.get_entity_mut(overlay) {
+        overlay.despawn_recursive();
+    }
+}
+
+fn end_status<S: StatusEffect>(commands: &mut Commands, entity: Entity, overlay: Entity) {
+    commands.entity(overlay).despawn_recursive();
+    commands.entity(entity).remove::<S>();
+}
+
+pub fn expire_status<S: StatusEffect>(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut S)>,
+) {
+    for (entity, mut status) in &mut query {
+        let status = status.status_mut();
+        status.remaining.tick(time.delta());
+
+        if status.remaining.finished() {
+            end_status::<S>(&mut commands, entity, status.overlay);
+        }
+    }
+}
+
+pub fn burn(
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Burning, &mut Health)>,
+    mut damaged: EventWriter<Damaged>,
+) {
+    for (entity, mut burning, mut health) in &mut query {
+        burning.next_burn.tick(time.delta());
+        if !burning.next_burn.just_finished() {
+            continue;
+        }
+
+        let amount = BURN_DAMAGE * burning.status.stacks as f32;
+        health.damage(amount);
+        damaged.send(Damaged { entity, amount });
+    }
+}
+
+pub fn levitate(mut commands: Commands, query: Query<(Entity, &Levitating), Without<Lifted>>) {
+    for (entity, levitating) in &query {
+        end_status::<Levitating>(&mut commands, entity, levitating.0.overlay);
+    }
+}
+
+pub fn freeze(mut query: Query<&mut Temperature, With<Frozen>>) {
+    for mut temperature in &mut query {
+        temperature.degrees = temperature.degrees.min(FREEZING_POINT);
+    }
+}
+
+pub fn squeeze(mut query: Query<&mut CharacterState, With<Compressed>>) {
+    for mut character in &mut query {
+        if character.action == Action::Running {
+            character.action = Action::Idle;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reapplying_follows_the_stacking_policy() {
+        let overlay = Entity::from_raw(1);
+        let half = Duration::from_secs(2);
+
+        let mut status = Status::new(Burning::DURATION, 10.0, overlay);
+        for power in [5.0, 20.0, 15.0, 10.0] {
+            status.remaining.tick(half);
+            status.reapply(Burning::STACKING, power);
+        }
+        assert_eq!(status.stacks, 3);
+        assert_eq!(status.power, 20.0);
+        assert_eq!(status.remaining.elapsed(), Duration::ZERO);
+
+        let mut status = Status::new(Compressed::DURATION, 10.0, overlay);
+        status.remaining.tick(half);
+        status.reapply(Compressed::STACKING, 30.0);
+        assert_eq!(status.stacks, 1);
+        assert_eq!(status.power, 10.0);
+        assert_eq!(status.remaining.elapsed(), half);
+
+        let mut status = Status::new(Frozen::DURATION, 10.0, overlay);
+        status.remaining.tick(half);
+        status.reapply(Frozen::STACKING, 10.0);
+        assert_eq!(status.stacks, 1);
+        assert_eq!(status.remaining.elapsed(), Duration::ZERO);
+    }
+}
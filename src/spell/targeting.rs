@@ -0,0 +1,130 @@
+use bevy::{
+    asset::{Assets, Handle},
+    ecs::{
+        entity::Entity,
+        query::With,
+        system::{Query, Res},
+    },
+    gizmos::gizmos::Gizmos,
+    math::{Rect, Vec2, Vec3},
+    render::color::Color,
+    sprite::{TextureAtlas, TextureAtlasSprite},
+    transform::components::GlobalTransform,
+};
+
+use crate::agent::human::HumanController;
+
+use super::focus::SelectedTarget;
+
+const HIGHLIGHT_COLOR: Color = Color::YELLOW;
+
+// Where the sprite's current atlas frame covers in the world
+pub fn frame_bounds(
+    atlas: &TextureAtlas,
+    sprite: &TextureAtlasSprite,
+    transform: &GlobalTransform,
+) -> Option<Rect> {
+    let frame = atlas.textures.get(sprite.index)?;
+    let (scale, _, translation) = transform.to_scale_rotation_translation();
+    let size = sprite.custom_size.unwrap_or(frame.size()) * scale.truncate().abs();
+    let centre = translation.truncate() - sprite.anchor.as_vec() * size;
+
+    Some(Rect::from_center_size(centre, size))
+}
+
+// Of the sprites under the point, the one drawn in front. Higher layers are in front, and within
+// a layer whatever stands lower on the screen is nearer the viewer.
+pub fn pick(
+    point: Vec2,
+    sprites: impl IntoIterator<Item = (Entity, Rect, Vec3)>,
+) -> Option<Entity> {
+    sprites
+        .into_iter()
+        .filter(|(_, bounds, _)| bounds.contains(point))
+        .max_by(|(_, _, a), (_, _, b)| a.z.total_cmp(&b.z).then(b.y.total_cmp(&a.y)))
+        .map(|(entity, _, _)| entity)
+}
+
+// Rings whatever the player has selected, at its feet
+pub fn draw_selection(
+    mut gizmos: Gizmos,
+    atlases: Res<Assets<TextureAtlas>>,
+    players: Query<&SelectedTarget, With<HumanController>>,
+    sprites: Query<(&GlobalTransform, &TextureAtlasSprite, &Handle<TextureAtlas>)>,
+) {
+    for selection in &players {
+        let Ok((transform, sprite, handle)) = sprites.get(selection.0) else {
+            continue;
+        };
+
+        let Some(bounds) = atlases
+            .get(handle)
+            .and_then(|atlas| frame_bounds(atlas, sprite, transform))
+        else {
+            continue;
+        };
+
+        gizmos.circle_2d(
+            Vec2::new(bounds.center().x, bounds.min.y),
+            bounds.width() / 2.0,
+            HIGHLIGHT_COLOR,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn picks_the_sprite_in_front() {
+        let tree = Entity::from_raw(1);
+        let rock = Entity::from_raw(2);
+        let fire = Entity::from_raw(3);
+
+        let sprites = [
+            (
+                tree,
+                Rect::new(-32.0, -16.0, 32.0, 112.0),
+                Vec3::new(0.0, 48.0, 10.0),
+            ),
+            (
+                rock,
+                Rect::new(-16.0, -32.0, 16.0, 0.0),
+                Vec3::new(0.0, -16.0, 10.0),
+            ),
+            (
+                fire,
+                Rect::new(200.0, 0.0, 232.0, 32.0),
+                Vec3::new(216.0, 16.0, 20.0),
+            ),
+        ];
+
+        // The rock stands lower down so it's in front of the tree where they overlap
+        assert_eq!(pick(Vec2::new(0.0, -8.0), sprites), Some(rock));
+        assert_eq!(pick(Vec2::new(0.0, 50.0), sprites), Some(tree));
+        assert_eq!(pick(Vec2::new(100.0, 0.0), sprites), None);
+
+        let mut raised = sprites;
+        raised[0].2.z = 20.0;
+        assert_eq!(pick(Vec2::new(0.0, -8.0), raised), Some(tree));
+    }
+
+    #[test]
+    fn frame_bounds_follow_the_anchor_and_scale() {
+        let mut atlas = TextureAtlas::new_empty(Handle::default(), Vec2::new(64.0, 64.0));
+        atlas.add_texture(Rect::new(0.0, 0.0, 32.0, 16.0));
+
+        let mut sprite = TextureAtlasSprite::new(0);
+        sprite.anchor = bevy::sprite::Anchor::BottomCenter;
+        let transform = GlobalTransform::from(
+            bevy::transform::components::Transform::from_xyz(100.0, 50.0, 10.0)
+                .with_scale(Vec3::splat(2.0)),
+        );
+
+        assert_eq!(
+            frame_bounds(&atlas, &sprite, &transform),
+            Some(Rect::new(68.0, 50.0, 132.0, 82.0))
+        );
+    }
+}
@@ -0,0 +1,295 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::{Event, EventReader, EventWriter},
+        query::{With, Without},
+        system::{Commands, Query, Res},
+        world::World,
+    },
+    math::Vec2,
+    render::color::Color,
+    sprite::TextureAtlasSprite,
+    time::Time,
+    transform::components::Transform,
+};
+use bevy_ecs_tilemap::{
+    helpers::square_grid::neighbors::Neighbors,
+    prelude::{TilePos, TileStorage, TilemapId},
+};
+
+use crate::{
+    terrain::{tile_at, MapLayer},
+    Game,
+};
+
+use super::{
+    interaction::ObjectKind,
+    lift::Lifted,
+    status::{afflict, Burning},
+};
+
+pub const AMBIENT: f32 = 20.0;
+pub const FREEZING_POINT: f32 = 0.0;
+pub const IGNITION_POINT: f32 = 300.0;
+pub const FIRE_TEMPERATURE: f32 = 600.0;
+
+// What a spell guard counts as hot or cold
+pub const HOT: f32 = 100.0;
+pub const COLD: f32 = FREEZING_POINT;
+
+// How much a single heat spell moves its target's temperature
+pub const HEAT_PER_CAST: f32 = 150.0;
+
+const SPREAD_RADIUS: f32 = 96.0;
+const CONDUCTION: f32 = 0.5;
+const GROUND_CONDUCTION: f32 = 0.5;
+const COOLING: f32 = 0.05;
+
+// Stops things flickering between states when they sit right on a threshold
+const HYSTERESIS: f32 = 10.0;
+
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Temperature {
+    pub degrees: f32,
+}
+
+impl Default for Temperature {
+    fn default() -> Self {
+        Temperature { degrees: AMBIENT }
+    }
+}
+
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ThermalState {
+    #[default]
+    Normal,
+    Ignited,
+    Frozen,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reaction {
+    Ignite,
+    Extinguish,
+    Freeze,
+    Melt,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ThermalReaction {
+    pub entity: Entity,
+    pub reaction: Reaction,
+}
+
+pub type ThermalBundle = (Temperature, ThermalState);
+
+// Anything touching the ground, as opposed to the ground itself or what's been lifted off it
+type OnTheGround = (Without<TilePos>, Without<Lifted>);
+
+pub fn new_thermal_bundle(degrees: f32) -> ThermalBundle {
+    (Temperature { degrees }, ThermalState::default())
+}
+
+impl ThermalState {
+    pub fn next(&self, degrees: f32) -> Option<(ThermalState, Reaction)> {
+        match self {
+            ThermalState::Normal if degrees >= IGNITION_POINT => {
+                Some((ThermalState::Ignited, Reaction::Ignite))
+            }
+            ThermalState::Normal if degrees <= FREEZING_POINT => {
+                Some((ThermalState::Frozen, Reaction::Freeze))
+            }
+            ThermalState::Ignited if degrees < IGNITION_POINT - HYSTERESIS => {
+                Some((ThermalState::Normal, Reaction::Extinguish))
+            }
+            ThermalState::Frozen if degrees > FREEZING_POINT + HYSTERESIS => {
+                Some((ThermalState::Normal, Reaction::Melt))
+            }
+            _ => None,
+        }
+    }
+
+    fn tint(&self) -> Color {
+        match self {
+            ThermalState::Normal => Color::WHITE,
+            ThermalState::Ignited => Color::rgb(1.0, 0.6, 0.4),
+            ThermalState::Frozen => Color::rgb(0.6, 0.8, 1.0),
+        }
+    }
+}
+
+pub fn change_temperature(commands: &mut Commands, entity: Entity, change: f32) {
+    commands.add(move |world: &mut World| {
+        if let Some(mut temperature) = world.get_mut::<Temperature>(entity) {
+            temperature.degrees += change;
+        }
+    });
+}
+
+pub fn spread_temperature(time: Res<Time>, mut query: Query<(&Transform, &mut Temperature)>) {
+    let delta = time.delta_seconds();
+
+    let snapshot = query
+        .iter()
+        .map(|(transform, temperature)| (transform.translation.truncate(), temperature.degrees))
+        .collect::<Vec<(Vec2, f32)>>();
+
+    for (transform, mut temperature) in &mut query {
+        let position = transform.translation.truncate();
+
+        let flow = snapshot
+            .iter()
+            .filter(|(other, _)| position.distance(*other) <= SPREAD_RADIUS)
+            .map(|(_, degrees)| (degrees - temperature.degrees) * CONDUCTION)
+            .sum::<f32>();
+
+        let cooling = (AMBIENT - temperature.degrees) * COOLING;
+
+        temperature.degrees += (flow + cooling) * delta;
+    }
+}
+
+pub fn spread_tile_temperature(
+    time: Res<Time>,
+    layers: Query<&TileStorage>,
+    tiles: Query<(Entity, &TilePos, &TilemapId)>,
+    mut temperatures: Query<&mut Temperature>,
+) {
+    let delta = time.delta_seconds();
+
+    let changes = tiles
+        .iter()
+        .filter_map(|(entity, tile_pos, tilemap_id)| {
+            let storage = layers.get(tilemap_id.0).ok()?;
+            let degrees = temperatures.get(entity).ok()?.degrees;
+
+            let flow = Neighbors::get_square_neighboring_positions(tile_pos, &storage.size, false)
+                .entities(storage)
+                .iter()
+                .filter_map(|neighbour| temperatures.get(*neighbour).ok())
+                .map(|neighbour| (neighbour.degrees - degrees) * CONDUCTION)
+                .sum::<f32>();
+
+            let cooling = (AMBIENT - degrees) * COOLING;
+
+            Some((entity, (flow + cooling) * delta))
+        })
+        .collect::<Vec<(Entity, f32)>>();
+
+    for (entity, change) in changes {
+        if let Ok(mut temperature) = temperatures.get_mut(entity) {
+            temperature.degrees += change;
+        }
+    }
+}
+
+// Heat passes between whatever is on the ground and the tiles under it, so fires warm the ground
+// and hot ground warms whatever stands on it
+pub fn exchange_ground_heat(
+    time: Res<Time>,
+    layers: Query<MapLayer>,
+    mut standing: Query<(&Transform, &mut Temperature), OnTheGround>,
+    mut tiles: Query<&mut Temperature, With<TilePos>>,
+) {
+    let delta = time.delta_seconds();
+
+    for (transform, mut temperature) in &mut standing {
+        let position = transform.translation.truncate();
+
+        for tile in layers.iter().filter_map(|layer| tile_at(layer, position)) {
+            let Ok(mut ground) = tiles.get_mut(tile) else {
+                continue;
+            };
+
+            let flow = (temperature.degrees - ground.degrees) * GROUND_CONDUCTION * delta;
+            temperature.degrees -= flow;
+            ground.degrees += flow;
+        }
+    }
+}
+
+type Reacting<'a> = (
+    Entity,
+    &'a Temperature,
+    &'a mut ThermalState,
+    Option<&'a mut TextureAtlasSprite>,
+    Option<&'a ObjectKind>,
+);
+
+pub fn react_to_temperature(
+    mut query: Query<Reacting>,
+    mut reactions: EventWriter<ThermalReaction>,
+) {
+    for (entity, temperature, mut state, sprite, kind) in &mut query {
+        let Some((next_state, reaction)) = state.next(temperature.degrees) else {
+            continue;
+        };
+
+        *state = next_state;
+
+        // Fires keep their own colour however hot or cold they get
+        let is_fire = matches!(kind, Some(ObjectKind::Fire | ObjectKind::Fireball));
+        if let Some(mut sprite) = sprite.filter(|_| !is_fire) {
+            sprite.color = state.tint();
+        }
+
+        reactions.send(ThermalReaction { entity, reaction });
+    }
+}
+
+// Trees and people catch fire and burn once they get hot enough, rocks and metal just get hot.
+// Nobody spent mana on it so any dispel puts it out.
+pub fn catch_fire(
+    mut commands: Commands,
+    game: Res<Game>,
+    mut reactions: EventReader<ThermalReaction>,
+    flammable: Query<&ObjectKind, With<TextureAtlasSprite>>,
+) {
+    let Some(entity_factory) = game.entity_factory.as_ref() else {
+        return;
+    };
+
+    for ThermalReaction { entity, reaction } in reactions.read() {
+        if *reaction != Reaction::Ignite {
+            continue;
+        }
+
+        if !matches!(
+            flammable.get(*entity),
+            Ok(ObjectKind::Tree | ObjectKind::Person)
+        ) {
+            continue;
+        }
+
+        afflict::<Burning>(&mut commands, entity_factory, *entity, 0.0);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn crosses_thresholds_in_both_directions() {
+        assert_eq!(
+            ThermalState::Normal.next(IGNITION_POINT),
+            Some((ThermalState::Ignited, Reaction::Ignite))
+        );
+        assert_eq!(ThermalState::Ignited.next(IGNITION_POINT - 1.0), None);
+        assert_eq!(
+            ThermalState::Ignited.next(AMBIENT),
+            Some((ThermalState::Normal, Reaction::Extinguish))
+        );
+
+        assert_eq!(
+            ThermalState::Normal.next(FREEZING_POINT - 5.0),
+            Some((ThermalState::Frozen, Reaction::Freeze))
+        );
+        assert_eq!(
+            ThermalState::Frozen.next(AMBIENT),
+            Some((ThermalState::Normal, Reaction::Melt))
+        );
+        assert_eq!(ThermalState::Normal.next(AMBIENT), None);
+    }
+}
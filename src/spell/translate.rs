@@ -0,0 +1,212 @@
+use std::{collections::HashMap, sync::mpsc::Sender};
+
+use bevy::ecs::{
+    entity::Entity,
+    event::{EventReader, EventWriter},
+    system::{Commands, Res, ResMut, Resource},
+};
+use uuid::Uuid;
+
+use crate::{
+    generator::Conversation,
+    oracle::{CompletionCallback, OracleMessage},
+    Game,
+};
+
+use super::{
+    parser::{parse_spell_in, resembles, Dialect, ExpectedRule, SpellParseError},
+    spellbook::Spellbook,
+    Rejection, Spell, SpellRejected,
+};
+
+// How many times the oracle gets to fix its own spell before we give up on it
+pub const MAX_TRANSLATION_ATTEMPTS: usize = 3;
+
+const TRANSLATION_PROMPT: &str = "You turn what a wizard says they want to do into a spell. \
+Reply with only the spell and nothing else. Spells must follow this pest grammar exactly:";
+
+const GRAMMAR: &str = include_str!("../../spellgrammar.pest");
+
+pub struct Translation {
+    caster: Entity,
+    dialect: Dialect,
+    incantation: String,
+    conversation: Conversation,
+    attempts: usize,
+}
+
+pub enum TranslationStep {
+    Cast(Box<Spell>),
+    Retry,
+    GiveUp(SpellParseError),
+}
+
+#[derive(Resource, Default)]
+pub struct PendingTranslations {
+    translations: HashMap<Uuid, Translation>,
+}
+
+impl Translation {
+    pub fn new(caster: Entity, dialect: Dialect, incantation: &str) -> Self {
+        let mut conversation =
+            Conversation::with_system_prompt(&format!("{}\n\n{}", TRANSLATION_PROMPT, GRAMMAR));
+        conversation.input_from_partner(incantation.to_string());
+
+        Translation {
+            caster,
+            dialect,
+            incantation: incantation.to_string(),
+            conversation,
+            attempts: 0,
+        }
+    }
+
+    // Checks the oracle's answer with the parser, anything that doesn't parse goes back to the
+    // oracle along with the parse error
+    pub fn review(&mut self, reply: &str) -> TranslationStep {
+        self.attempts += 1;
+
+        let spell = reply.trim().trim_matches(|c| c == '`' || c == '"').trim();
+
+        let error = match parse_spell_in(spell, self.dialect) {
+            Ok(ast) => {
+                let spell = Spell::from(ast)
+                    .with_source(&self.incantation)
+                    .with_translation(spell)
+                    .cast_by(self.caster);
+                return TranslationStep::Cast(Box::new(spell));
+            }
+            Err(error) => error,
+        };
+
+        // The player never saw the oracle's attempts, so the error is for what they said
+        if self.attempts >= MAX_TRANSLATION_ATTEMPTS {
+            let error = parse_spell_in(&self.incantation, self.dialect)
+                .err()
+                .unwrap_or(error);
+            return TranslationStep::GiveUp(error);
+        }
+
+        self.conversation.input_from_self(reply.to_string());
+        self.conversation.input_from_partner(format!(
+            "That isn't a valid spell. {} Reply with only the corrected spell.",
+            error
+        ));
+
+        TranslationStep::Retry
+    }
+}
+
+// Anything that starts like a spell is the caster's own go at one, and they'd rather hear what's
+// wrong with it than have the oracle guess. Only what starts some other way gets translated.
+pub fn is_free_text(incantation: &str, spellbook: &Spellbook) -> bool {
+    let Some(first) = incantation.split_whitespace().next() else {
+        return false;
+    };
+    let first = first.to_lowercase();
+
+    !(first == "if" || resembles(&first, ExpectedRule::Effect) || spellbook.get(&first).is_some())
+}
+
+impl PendingTranslations {
+    pub fn start(
+        &mut self,
+        asker: &Sender<OracleMessage>,
+        caster: Entity,
+        dialect: Dialect,
+        incantation: &str,
+    ) {
+        self.ask(asker, Translation::new(caster, dialect, incantation));
+    }
+
+    fn ask(&mut self, asker: &Sender<OracleMessage>, translation: Translation) {
+        let id = Uuid::new_v4();
+
+        asker
+            .send((id, translation.conversation.clone().into()))
+            .expect("Channel send failed");
+
+        self.translations.insert(id, translation);
+    }
+}
+
+pub fn receive_translations(
+    mut commands: Commands,
+    game: Res<Game>,
+    mut pending: ResMut<PendingTranslations>,
+    mut completions: EventReader<CompletionCallback>,
+    mut rejections: EventWriter<SpellRejected>,
+) {
+    for completion in completions.read() {
+        let Some(mut translation) = pending.translations.remove(&completion.id) else {
+            continue;
+        };
+
+        match translation.review(&completion.message) {
+            TranslationStep::Cast(spell) => {
+                commands.spawn(*spell);
+            }
+            TranslationStep::Retry => pending.ask(&game.asker, translation),
+            TranslationStep::GiveUp(error) => {
+                rejections.send(SpellRejected {
+                    caster: Some(translation.caster),
+                    rejection: Rejection::Untranslated(error),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn retries_until_the_reply_parses_or_attempts_run_out() {
+        let caster = Entity::from_raw(1);
+
+        let mut translation = Translation::new(
+            caster,
+            Dialect::LATEST,
+            "set the skeleton on fire and throw it",
+        );
+        assert!(matches!(
+            translation.review("burn the skeleton"),
+            TranslationStep::Retry
+        ));
+        let TranslationStep::Cast(spell) = translation.review("`heat target then push it back`")
+        else {
+            panic!("Expected the corrected spell to be cast");
+        };
+        assert_eq!(spell.source, "set the skeleton on fire and throw it");
+        assert_eq!(
+            spell.translation.as_deref(),
+            Some("heat target then push it back")
+        );
+
+        let mut translation = Translation::new(caster, Dialect::LATEST, "do something");
+        for _ in 1..MAX_TRANSLATION_ATTEMPTS {
+            assert!(matches!(
+                translation.review("nonsense"),
+                TranslationStep::Retry
+            ));
+        }
+        let TranslationStep::GiveUp(error) = translation.review("nonsense") else {
+            panic!("Expected to give up");
+        };
+        assert_eq!(error.found, "do");
+        assert_eq!(error.span, 0..2);
+    }
+
+    #[test]
+    fn only_free_text_is_translated() {
+        let spellbook = Spellbook::default();
+
+        assert!(is_free_text("set the skeleton on fire", &spellbook));
+        assert!(is_free_text("Throw that rock at him", &spellbook));
+        assert!(!is_free_text("heat target then push it bak", &spellbook));
+        assert!(!is_free_text("lfit me", &spellbook));
+        assert!(!is_free_text("if target is hot then lift it", &spellbook));
+        assert!(!is_free_text("   ", &spellbook));
+    }
+}
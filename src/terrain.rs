@@ -5,24 +5,33 @@ use std::sync::Arc;
 use bevy::{
     asset::{io::Reader, AssetLoader, AssetPath, AsyncReadExt},
     log,
+    math::Vec2,
     prelude::{
         Added, Asset, AssetApp, AssetEvent, AssetId, Assets, Bundle, Commands, Component,
         DespawnRecursiveExt, Entity, EventReader, GlobalTransform, Handle, Image, Plugin, Query,
-        Res, Transform, Update,
+        Res, Resource, Transform, Update, World,
     },
     reflect::TypePath,
     utils::{BoxedFuture, HashMap},
 };
 use bevy_ecs_tilemap::prelude::*;
 
+use serde::{Deserialize, Serialize};
+
+use crate::spell::temperature::{new_thermal_bundle, AMBIENT};
+
 use thiserror::Error;
 
+// Where the marks spells have left on the map are kept between games
+pub const TERRAIN_ALTERATIONS: &str = "terrain.ron";
+
 #[derive(Default)]
 pub struct TiledMapPlugin;
 
 impl Plugin for TiledMapPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.init_asset::<TiledMap>()
+            .init_resource::<TerrainAlterations>()
             .register_asset_loader(TiledLoader)
             .add_systems(Update, process_loaded_maps);
     }
@@ -45,6 +54,204 @@ pub struct TiledLayersStorage {
     pub storage: HashMap<u32, Entity>,
 }
 
+// Tiles marked with a `blocking` bool property in Tiled, nothing can be pushed through them
+#[derive(Component)]
+pub struct Blocking;
+
+// Everything needed to work out which tile of a layer sits under a point in the world
+pub type MapLayer<'a> = (
+    &'a TilemapSize,
+    &'a TilemapGridSize,
+    &'a TilemapType,
+    &'a TileStorage,
+    &'a Transform,
+);
+
+// Which of the map's layers a layer entity was made from
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LayerIndex(pub u32);
+
+// Lasting marks spells leave on the ground
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TerrainAlteration {
+    Scorched,
+    Frozen,
+    Cratered,
+}
+
+impl TerrainAlteration {
+    // The tile property in Tiled holding the id of the tile to swap to
+    fn property(&self) -> &'static str {
+        match self {
+            TerrainAlteration::Scorched => "scorched",
+            TerrainAlteration::Frozen => "frozen",
+            TerrainAlteration::Cratered => "cratered",
+        }
+    }
+}
+
+// The texture indexes a tile can be swapped to, only tiles that have at least one get this
+#[derive(Component, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TileVariants(HashMap<TerrainAlteration, u32>);
+
+impl TileVariants {
+    pub fn get(&self, alteration: TerrainAlteration) -> Option<u32> {
+        self.0.get(&alteration).copied()
+    }
+}
+
+// Every tile spells have changed, by layer and position, so reloading the map puts them back
+#[derive(Resource, Default, Debug)]
+pub struct TerrainAlterations(pub HashMap<(u32, TilePos), TerrainAlteration>);
+
+#[derive(Debug, Error)]
+pub enum TerrainAlterationsError {
+    #[error("Could not access terrain alterations: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not read terrain alterations: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("Could not write terrain alterations: {0}")]
+    Write(#[from] ron::Error),
+}
+
+// Saved as (layer, x, y, alteration), since tile positions can't be written out themselves
+type SavedAlteration = (u32, u32, u32, TerrainAlteration);
+
+impl TerrainAlterations {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TerrainAlterationsError> {
+        let text = std::fs::read_to_string(path)?;
+        let saved: Vec<SavedAlteration> = ron::de::from_str(&text)?;
+
+        Ok(TerrainAlterations(
+            saved
+                .into_iter()
+                .map(|(layer, x, y, alteration)| ((layer, TilePos { x, y }), alteration))
+                .collect(),
+        ))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), TerrainAlterationsError> {
+        let mut saved = self
+            .0
+            .iter()
+            .map(|((layer, tile_pos), alteration)| (*layer, tile_pos.x, tile_pos.y, *alteration))
+            .collect::<Vec<SavedAlteration>>();
+        saved.sort_by_key(|(layer, x, y, _)| (*layer, *y, *x));
+
+        let text = ron::ser::to_string_pretty(&saved, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+pub fn alter_terrain(commands: &mut Commands, position: Vec2, alteration: TerrainAlteration) {
+    commands.add(move |world: &mut World| alter_tiles(world, position, alteration));
+}
+
+// Tiles without a variant for the alteration are left as they are
+fn alter_tiles(world: &mut World, position: Vec2, alteration: TerrainAlteration) {
+    let mut layers = world.query::<(MapLayer, &LayerIndex)>();
+    let tiles = layers
+        .iter(world)
+        .filter_map(|(layer, index)| Some((tile_at(layer, position)?, *index)))
+        .collect::<Vec<(Entity, LayerIndex)>>();
+
+    for (tile, LayerIndex(layer)) in tiles {
+        let Some(mut tile) = world.get_entity_mut(tile) else {
+            continue;
+        };
+
+        let Some(variant) = tile
+            .get::<TileVariants>()
+            .and_then(|variants| variants.get(alteration))
+        else {
+            continue;
+        };
+
+        let Some(tile_pos) = tile.get::<TilePos>().copied() else {
+            continue;
+        };
+
+        if let Some(mut texture_index) = tile.get_mut::<TileTextureIndex>() {
+            texture_index.0 = variant;
+        }
+
+        world
+            .get_resource_or_insert_with(TerrainAlterations::default)
+            .0
+            .insert((layer, tile_pos), alteration);
+    }
+}
+
+// Where a tile of the tileset ends up in the layer's texture
+fn texture_index_of(
+    tiled_map: &TiledMap,
+    tilemap_texture: &TilemapTexture,
+    tileset_index: usize,
+    tile_id: tiled::TileId,
+) -> Option<u32> {
+    match tilemap_texture {
+        TilemapTexture::Single(_) => Some(tile_id),
+        #[cfg(not(feature = "atlas"))]
+        TilemapTexture::Vector(_) => tiled_map
+            .tile_image_offsets
+            .get(&(tileset_index, tile_id))
+            .copied(),
+        #[cfg(not(feature = "atlas"))]
+        _ => unreachable!(),
+    }
+}
+
+// Reads the scorched, frozen and cratered tile properties of every tile in a tileset
+fn tileset_variants(
+    tiled_map: &TiledMap,
+    tilemap_texture: &TilemapTexture,
+    tileset_index: usize,
+    tileset: &tiled::Tileset,
+) -> HashMap<tiled::TileId, TileVariants> {
+    let alterations = [
+        TerrainAlteration::Scorched,
+        TerrainAlteration::Frozen,
+        TerrainAlteration::Cratered,
+    ];
+
+    tileset
+        .tiles()
+        .filter_map(|(tile_id, tile)| {
+            let variants = alterations
+                .iter()
+                .filter_map(|alteration| {
+                    let Some(tiled::PropertyValue::IntValue(variant)) =
+                        tile.properties.get(alteration.property())
+                    else {
+                        return None;
+                    };
+
+                    let variant = tiled::TileId::try_from(*variant).ok()?;
+                    let index =
+                        texture_index_of(tiled_map, tilemap_texture, tileset_index, variant)?;
+                    Some((*alteration, index))
+                })
+                .collect::<HashMap<_, _>>();
+
+            (!variants.is_empty()).then_some((tile_id, TileVariants(variants)))
+        })
+        .collect()
+}
+
+pub fn tile_at(layer: MapLayer, world_position: Vec2) -> Option<Entity> {
+    let (map_size, grid_size, map_type, tile_storage, transform) = layer;
+
+    let local_position = transform
+        .compute_matrix()
+        .inverse()
+        .transform_point3(world_position.extend(0.0))
+        .truncate();
+
+    let tile_pos = TilePos::from_world_pos(&local_position, map_size, grid_size, map_type)?;
+    tile_storage.get(&tile_pos)
+}
+
 #[derive(Default, Bundle)]
 pub struct TiledMapBundle {
     pub tiled_map: Handle<TiledMap>,
@@ -193,6 +400,7 @@ pub fn process_loaded_maps(
         &TilemapRenderSettings,
     )>,
     new_maps: Query<&Handle<TiledMap>, Added<Handle<TiledMap>>>,
+    alterations: Res<TerrainAlterations>,
 ) {
     let mut changed_maps = Vec::<AssetId<TiledMap>>::default();
     for event in map_events.read() {
@@ -259,6 +467,9 @@ pub fn process_loaded_maps(
                         y: tileset.spacing as f32,
                     };
 
+                    let variants =
+                        tileset_variants(tiled_map, tilemap_texture, tileset_index, tileset);
+
                     // Once materials have been created/added we need to then create the layers.
                     for (layer_index, layer) in tiled_map.map.layers().enumerate() {
                         let offset_x = layer.offset_x;
@@ -342,19 +553,46 @@ pub fn process_loaded_maps(
                                 };
 
                                 let tile_pos = TilePos { x, y };
+                                let tile_variants = variants.get(&layer_tile.id());
+
+                                // Put back whatever spells did to the tile before the reload
+                                let texture_index = alterations
+                                    .0
+                                    .get(&(layer_index as u32, tile_pos))
+                                    .and_then(|alteration| tile_variants?.get(*alteration))
+                                    .unwrap_or(texture_index);
+
                                 let tile_entity = commands
-                                    .spawn(TileBundle {
-                                        position: tile_pos,
-                                        tilemap_id: TilemapId(layer_entity),
-                                        texture_index: TileTextureIndex(texture_index),
-                                        flip: TileFlip {
-                                            x: layer_tile_data.flip_h,
-                                            y: layer_tile_data.flip_v,
-                                            d: layer_tile_data.flip_d,
+                                    .spawn((
+                                        TileBundle {
+                                            position: tile_pos,
+                                            tilemap_id: TilemapId(layer_entity),
+                                            texture_index: TileTextureIndex(texture_index),
+                                            flip: TileFlip {
+                                                x: layer_tile_data.flip_h,
+                                                y: layer_tile_data.flip_v,
+                                                d: layer_tile_data.flip_d,
+                                            },
+                                            ..Default::default()
                                         },
-                                        ..Default::default()
-                                    })
+                                        new_thermal_bundle(AMBIENT),
+                                    ))
                                     .id();
+
+                                let blocking = layer_tile.get_tile().is_some_and(|tile| {
+                                    matches!(
+                                        tile.properties.get("blocking"),
+                                        Some(tiled::PropertyValue::BoolValue(true))
+                                    )
+                                });
+                                if blocking {
+                                    commands.entity(tile_entity).insert(Blocking);
+                                }
+
+                                if let Some(tile_variants) = tile_variants {
+                                    commands.entity(tile_entity).insert(tile_variants.clone());
+                                }
+
                                 tile_storage.set(&tile_pos, tile_entity);
                             }
                         }
@@ -376,6 +614,9 @@ pub fn process_loaded_maps(
                             render_settings: *render_settings,
                             ..Default::default()
                         });
+                        commands
+                            .entity(layer_entity)
+                            .insert(LayerIndex(layer_index as u32));
 
                         layer_storage
                             .storage
@@ -386,3 +627,51 @@ pub fn process_loaded_maps(
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn altering_swaps_the_tile_and_records_it() {
+        let mut world = World::new();
+
+        let map_size = TilemapSize { x: 2, y: 2 };
+        let grid_size = TilemapGridSize { x: 64.0, y: 64.0 };
+        let tile_pos = TilePos { x: 1, y: 0 };
+
+        let variants = TileVariants(HashMap::from_iter([(TerrainAlteration::Scorched, 32)]));
+        let tile = world.spawn((tile_pos, TileTextureIndex(0), variants)).id();
+        let mut tile_storage = TileStorage::empty(map_size);
+        tile_storage.set(&tile_pos, tile);
+
+        world.spawn((
+            map_size,
+            grid_size,
+            TilemapType::Square,
+            tile_storage,
+            Transform::default(),
+            LayerIndex(2),
+        ));
+
+        let over_tile = tile_pos.center_in_world(&grid_size, &TilemapType::Square);
+        let texture_index = |world: &World| world.get::<TileTextureIndex>(tile).unwrap().0;
+
+        // Grass has nothing to freeze into
+        alter_tiles(&mut world, over_tile, TerrainAlteration::Frozen);
+        assert_eq!(texture_index(&world), 0);
+        assert!(world.get_resource::<TerrainAlterations>().is_none());
+
+        alter_tiles(&mut world, over_tile, TerrainAlteration::Scorched);
+        assert_eq!(texture_index(&world), 32);
+        assert_eq!(
+            world.resource::<TerrainAlterations>().0.get(&(2, tile_pos)),
+            Some(&TerrainAlteration::Scorched)
+        );
+
+        let path = std::env::temp_dir().join("spellfire-terrain-test.ron");
+        world.resource::<TerrainAlterations>().save(&path).unwrap();
+        let loaded = TerrainAlterations::load(&path).unwrap();
+        assert_eq!(loaded.0, world.resource::<TerrainAlterations>().0);
+    }
+}
@@ -2,6 +2,32 @@
 <tileset version="1.10" tiledversion="1.10.2" name="map" tilewidth="64" tileheight="32" tilecount="672" columns="16">
  <grid orientation="orthogonal" width="32" height="32"/>
  <image source="../../Working/map.png" width="1024" height="1344"/>
+ <tile id="0">
+  <properties>
+   <property name="cratered" type="int" value="92"/>
+   <property name="frozen" type="int" value="93"/>
+   <property name="scorched" type="int" value="94"/>
+  </properties>
+ </tile>
+ <tile id="1">
+  <properties>
+   <property name="cratered" type="int" value="92"/>
+   <property name="frozen" type="int" value="93"/>
+   <property name="scorched" type="int" value="94"/>
+  </properties>
+ </tile>
+ <tile id="2">
+  <properties>
+   <property name="cratered" type="int" value="92"/>
+   <property name="frozen" type="int" value="93"/>
+   <property name="scorched" type="int" value="94"/>
+  </properties>
+ </tile>
+ <tile id="304">
+  <properties>
+   <property name="blocking" type="bool" value="true"/>
+  </properties>
+ </tile>
  <wangsets>
   <wangset name="Unnamed Set" type="corner" tile="-1">
    <wangcolor name="" color="#ff0000" tile="-1" probability="1"/>